@@ -0,0 +1,23 @@
+//! Feeds arbitrary bytes to every single-block JSON entry point. None may panic or run away.
+//!
+//! `cargo +nightly fuzz run block_json`
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use simple_blockchain::block::Block;
+use simple_blockchain::blockchain::Blockchain;
+use simple_blockchain::compat::from_tutorial_json;
+use simple_blockchain::difficulty::Difficulty;
+use simple_blockchain::params::ChainParams;
+use simple_blockchain::relay::RelayedEntry;
+use simple_blockchain::template::BlockTemplate;
+
+fuzz_target!(|data: &[u8]| {
+  let Ok(json) = std::str::from_utf8(data) else { return };
+  let _ = BlockTemplate::<String>::from_json(json);
+  let _ = from_tutorial_json(json);
+  let _ = RelayedEntry::from_data(json);
+
+  let mut chain = Blockchain::<Block>::with_params(ChainParams { difficulty: Difficulty(0), ..Default::default() });
+  chain.genesis().unwrap();
+  let _ = chain.submit_mined(json);
+});
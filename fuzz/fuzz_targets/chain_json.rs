@@ -0,0 +1,15 @@
+//! Feeds arbitrary bytes to the whole-chain JSON entry points. None may panic or run away.
+//!
+//! `cargo +nightly fuzz run chain_json`
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use simple_blockchain::block::Block;
+use simple_blockchain::compat::chain_from_tutorial_json;
+use simple_blockchain::decode::{ from_json, DecodeLimits };
+
+fuzz_target!(|data: &[u8]| {
+  let _ = from_json::<Vec<Block>>(data, DecodeLimits::for_chain());
+  if let Ok(json) = std::str::from_utf8(data) {
+    let _ = chain_from_tutorial_json(json);
+  }
+});
@@ -0,0 +1,56 @@
+/* C bindings for simple-blockchain, built with
+ * `cargo rustc --release --features capi --crate-type cdylib`.
+ * Keep in sync with src/ffi.rs.
+ *
+ * Blocks and chains cross the boundary as JSON strings.
+ * Strings returned by these functions must be freed with sb_string_free. */
+#ifndef SIMPLE_BLOCKCHAIN_H
+#define SIMPLE_BLOCKCHAIN_H
+
+#include <stdint.h>
+
+/* The hardest difficulty these functions accept. Mining can't be interrupted,
+ * so a harder chain would never return from sb_mine_block. */
+#define SB_MAX_DIFFICULTY 32
+
+#define SB_OK 0
+#define SB_ERR_NULL -1
+#define SB_ERR_UTF8 -2
+#define SB_ERR_INVALID_CHAIN_LENGTH 1
+#define SB_ERR_MALFORMED_JSON 2
+#define SB_ERR_CHECKSUM_MISMATCH 3
+#define SB_ERR_IO 4
+#define SB_ERR_UNKNOWN_CHAIN 5
+#define SB_ERR_INPUT_TOO_LARGE 6
+#define SB_ERR_INPUT_TOO_DEEP 7
+#define SB_ERR_ID_OUT_OF_SEQUENCE 10
+#define SB_ERR_PREVIOUS_HASH_MISMATCH 11
+#define SB_ERR_TOO_LARGE 12
+#define SB_ERR_INVALID_TIMESTAMP 13
+#define SB_ERR_DIFFICULTY_NOT_MET 14
+#define SB_ERR_HASH_MISMATCH 15
+#define SB_ERR_CUSTOM_RULE 16
+#define SB_ERR_INVALID_SIGNATURE 17
+
+typedef struct SbBlockchain SbBlockchain;
+
+/* Creates a chain at `difficulty` leading zero bits, with its genesis block mined.
+ * Returns NULL if `difficulty` is over SB_MAX_DIFFICULTY. */
+SbBlockchain *sb_blockchain_new(uint32_t difficulty);
+/* Frees a chain. Does nothing if `chain` is NULL. */
+void sb_blockchain_free(SbBlockchain *chain);
+/* Mines the next block with `data` without adding it. Returns its JSON, or NULL. */
+char *sb_mine_block(const SbBlockchain *chain, const char *data);
+/* Validates and adds a block given as JSON. Returns SB_OK or an SB_ERR_* code. */
+int32_t sb_add_block(SbBlockchain *chain, const char *block_json);
+/* Returns 1 if every block is valid, 0 if not (including genesis only), or SB_ERR_NULL. */
+int32_t sb_validate(const SbBlockchain *chain);
+/* Returns the chain's blocks as a JSON array, or NULL. */
+char *sb_blockchain_to_json(const SbBlockchain *chain);
+/* Loads a chain at `difficulty` from a JSON array of blocks.
+ * Returns NULL if it has no blocks, any block isn't valid, or `difficulty` is over SB_MAX_DIFFICULTY. */
+SbBlockchain *sb_blockchain_from_json(const char *blocks_json, uint32_t difficulty);
+/* Frees a string returned by these functions. Does nothing if `string` is NULL. */
+void sb_string_free(char *string);
+
+#endif
@@ -0,0 +1,144 @@
+use std::fs;
+use std::path::Path;
+use sha2::{ Sha256, Digest };
+use crate::block::{ Block, BlockData };
+use crate::blockchain::Blockchain;
+use crate::decode::{ self, DecodeLimits };
+use crate::error::{ BlockchainError, BlockchainError::* };
+
+/// The file a backup's blocks are written to, as a JSON array.
+pub const CHAIN_FILE: &str = "chain.json";
+/// The file holding the hex SHA-256 checksum of [`CHAIN_FILE`].
+pub const CHECKSUM_FILE: &str = "chain.json.sha256";
+
+impl<T: BlockData> Blockchain<Block<T>> {
+  /// Writes the blocks and their checksum into `dir`, creating it if needed.
+  ///
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::blockchain::Blockchain;
+  /// # let dir = std::env::temp_dir().join(format!("simple_blockchain_doc_backup_{}", std::process::id()));
+  /// let mut my_blockchain = Blockchain::new();
+  /// my_blockchain.genesis();
+  /// my_blockchain.backup(&dir).unwrap();
+  ///
+  /// let mut restored = Blockchain::new();
+  /// assert_eq!(restored.restore(&dir), Ok(()));
+  /// assert_eq!(restored.blocks, my_blockchain.blocks);
+  /// # std::fs::remove_dir_all(&dir).unwrap();
+  /// ```
+  ///
+  /// # Errors
+  /// Returns [`BlockchainError::Io`] if the files can't be written.
+  pub fn backup(&self, dir: impl AsRef<Path>) -> Result<(), BlockchainError> {
+    let dir = dir.as_ref();
+    let json = serde_json::to_vec(&self.blocks).unwrap();
+    fs::create_dir_all(dir).map_err(io_error)?;
+    fs::write(dir.join(CHAIN_FILE), &json).map_err(io_error)?;
+    fs::write(dir.join(CHECKSUM_FILE), checksum(&json)).map_err(io_error)
+  }
+
+  /// Checks a backup in `dir` without restoring it: the checksum must match,
+  /// and every block must be valid under this blockchain's parameters.
+  ///
+  /// # Errors
+  /// Returns [`BlockchainError::Io`] if the files can't be read,
+  /// [`BlockchainError::ChecksumMismatch`] if the blocks don't match their checksum,
+  /// [`BlockchainError::InputTooLarge`] or [`BlockchainError::InputTooDeep`] if they're over [`DecodeLimits::for_chain`],
+  /// [`BlockchainError::MalformedJson`] if they can't be parsed,
+  /// and [`BlockchainError::InvalidBlock`] for the first invalid block.
+  pub fn verify_backup(&self, dir: impl AsRef<Path>) -> Result<(), BlockchainError> {
+    self.read_backup(dir.as_ref()).map(|_| ())
+  }
+
+  /// Replaces the blocks with the backup in `dir`, once [`Blockchain::verify_backup`] passes.
+  /// The blocks are left untouched if it doesn't.
+  ///
+  /// # Errors
+  /// Returns the same errors as [`Blockchain::verify_backup`].
+  pub fn restore(&mut self, dir: impl AsRef<Path>) -> Result<(), BlockchainError> {
+    self.blocks = self.read_backup(dir.as_ref())?;
+    self.debug_check_invariants();
+    Ok(())
+  }
+
+  fn read_backup(&self, dir: &Path) -> Result<Vec<Block<T>>, BlockchainError> {
+    let json = fs::read(dir.join(CHAIN_FILE)).map_err(io_error)?;
+    let expected = fs::read_to_string(dir.join(CHECKSUM_FILE)).map_err(io_error)?;
+    if checksum(&json) != expected.trim() { return Err(ChecksumMismatch) };
+
+    let blocks: Vec<Block<T>> = decode::from_json(&json, DecodeLimits::for_chain())?;
+    if let Some(genesis) = blocks.first() { self.validate_genesis(genesis)? };
+    for pair in blocks.windows(2) {
+      self.validate_block(&pair[1], &pair[0])?;
+    }
+    Ok(blocks)
+  }
+}
+
+fn checksum(bytes: &[u8]) -> String {
+  hex::encode(Sha256::digest(bytes))
+}
+
+fn io_error(error: std::io::Error) -> BlockchainError {
+  Io(error.to_string())
+}
+
+#[test]
+fn rejects_a_tampered_backup() {
+  let dir = std::env::temp_dir().join(format!("simple_blockchain_tampered_{}", std::process::id()));
+  let mut chain = Blockchain::new();
+  assert!(chain.genesis().is_ok());
+  assert!(chain.backup(&dir).is_ok());
+  let json = fs::read_to_string(dir.join(CHAIN_FILE)).unwrap();
+  fs::write(dir.join(CHAIN_FILE), json.replace("genesis!", "tampered")).unwrap();
+
+  let mut restored = Blockchain::new();
+  let result = restored.restore(&dir);
+  fs::remove_dir_all(&dir).unwrap();
+  assert_eq!(result, Err(ChecksumMismatch));
+  assert!(restored.blocks.is_empty());
+}
+
+#[test]
+fn rejects_a_backup_of_invalid_blocks() {
+  use crate::error::BlockValidationError;
+  let dir = std::env::temp_dir().join(format!("simple_blockchain_invalid_{}", std::process::id()));
+  let mut chain = Blockchain::new();
+  assert!(chain.genesis().is_ok());
+  chain.blocks.push(Block::new(1, "not_the_previous_hash", "next".to_string()));
+  assert!(chain.backup(&dir).is_ok());
+
+  let result = Blockchain::new().verify_backup(&dir);
+  fs::remove_dir_all(&dir).unwrap();
+  assert_eq!(result, Err(InvalidBlock(BlockValidationError::PreviousHashMismatch)));
+}
+
+#[test]
+fn reports_missing_backups() {
+  let dir = std::env::temp_dir().join(format!("simple_blockchain_missing_{}", std::process::id()));
+  assert!(matches!(Blockchain::new().verify_backup(&dir), Err(Io(_))));
+}
+
+#[test]
+fn backs_up_chains_of_any_data() {
+  use crate::crypto::Keypair;
+  use crate::difficulty::Difficulty;
+  use crate::params::ChainParams;
+  use crate::transaction::{ Mempool, Transaction };
+  use crate::types::Amount;
+  let dir = std::env::temp_dir().join(format!("simple_blockchain_ledger_{}", std::process::id()));
+  let params = ChainParams { difficulty: Difficulty::DEMO, ..Default::default() };
+  let mut ledger = Blockchain::with_params(params.clone());
+  assert!(ledger.genesis_with(vec![]).is_ok());
+  let mut mempool = Mempool::new();
+  mempool.add(Transaction::signed(&Keypair::from_seed([1; 32]), "bob", Amount(5), 0));
+  assert_eq!(ledger.mine_pending(&mut mempool), Ok(1));
+  assert!(ledger.backup(&dir).is_ok());
+
+  let mut restored = Blockchain::with_params(params);
+  let result = restored.restore(&dir);
+  fs::remove_dir_all(&dir).unwrap();
+  assert_eq!(result, Ok(()));
+  assert_eq!(restored.blocks, ledger.blocks);
+}
@@ -1,18 +1,76 @@
-use serde::{ Serialize, Deserialize };
-use chrono::Utc;
-use crate::helpers::*;
+use std::fmt::Debug;
+use serde::{ Serialize, Deserialize, de::DeserializeOwned };
+use serde_json::Value;
+use chrono::{ DateTime, Utc };
+use crate::difficulty::Difficulty;
+use crate::helpers::hash_preimage;
+use crate::template::BlockTemplate;
+use crate::timestamp::{ self, TimestampFormat };
+use crate::types::{ BlockHash, BlockId };
+
+/// A payload a [`Block`] can carry.
+/// 
+/// Blocks are hashed over [`BlockData::canonical`] rather than the data itself,
+/// so two equal payloads always hash the same however they were built.
+/// 
+/// # Examples
+/// ```
+/// # use serde::{ Serialize, Deserialize };
+/// # use simple_blockchain::block::{ Block, BlockData };
+/// #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+/// struct Transfer {
+///   from: String,
+///   to: String,
+///   amount: u64,
+/// }
+///
+/// impl BlockData for Transfer {}
+///
+/// let transfer = Transfer { from: "alice".to_string(), to: "bob".to_string(), amount: 5 };
+/// let block = Block::new(1, "genesis", transfer.clone());
+/// assert_eq!(block.data, transfer);
+/// assert!(block.hash.starts_with("0000"));
+/// ```
+pub trait BlockData: Serialize + DeserializeOwned + Debug + Clone + PartialEq + Send + Sync + 'static {
+  /// Returns the JSON value hashed in the data's place.
+  /// 
+  /// Defaults to the data serialized as JSON. Objects come out with their keys sorted,
+  /// so the value doesn't depend on field order.
+  /// 
+  /// # Panics
+  /// The default panics if the data can't be represented as JSON, e.g. a map with non-string keys.
+  fn canonical(&self) -> Value {
+    serde_json::to_value(self).expect("block data must serialize to JSON")
+  }
+}
+
+/// Hashes as a JSON string, so string blocks hash exactly as they always have.
+impl BlockData for String {}
+
+impl BlockData for Value {}
+
+/// Names the data type of a block, so a [`Blockchain`](crate::blockchain::Blockchain) can find its params' type.
+pub trait ChainBlock {
+  type Data: BlockData;
+}
+
+impl<T: BlockData> ChainBlock for Block<T> {
+  type Data = T;
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
-pub struct Block {
-  pub id: u64,
-  pub hash: String,
-  pub previous_hash: String,
+pub struct Block<T = String> {
+  pub id: BlockId,
+  pub hash: BlockHash,
+  pub previous_hash: BlockHash,
+  /// Seconds since the Unix epoch. Deserializes from an RFC 3339 string too.
+  #[serde(deserialize_with = "timestamp::deserialize")]
   pub timestamp: i64,
-  pub data: String,
+  pub data: T,
   pub nonce: u64,
 }
 
-impl Block {
+impl<T: BlockData> Block<T> {
   /// Creates a new block by adding a timestamp and mining a hash.
   /// 
   /// # Examples
@@ -29,10 +87,97 @@ impl Block {
   /// assert_eq!(new_block.id, 1);
   /// assert_eq!(new_block.data, "new");
   /// ```
-  pub fn new(id: u64, previous_hash: &String, data: String) -> Self {
-    let timestamp = Utc::now().timestamp();
-    let (nonce, hash) = mine_hash(id, timestamp, previous_hash, &data);
-    Self { id, hash, previous_hash: previous_hash.clone(), timestamp, data, nonce }
+  pub fn new(id: impl Into<BlockId>, previous_hash: &str, data: T) -> Self {
+    Self::new_at(id, previous_hash, data, Utc::now())
+  }
+
+  /// Creates a new block stamped with the given time, to the second, and mines a hash.
+  /// 
+  /// # Examples
+  /// ```
+  /// # use chrono::{ TimeZone, Utc };
+  /// # use simple_blockchain::block::Block;
+  /// let at = Utc.with_ymd_and_hms(2022, 1, 26, 19, 1, 9).unwrap();
+  /// let new_block = Block::new_at(1, "genesis", "new".to_string(), at);
+  /// assert_eq!(new_block.timestamp, 1643223669);
+  /// assert_eq!(new_block.datetime(), at);
+  /// ```
+  pub fn new_at(id: impl Into<BlockId>, previous_hash: &str, data: T, at: DateTime<Utc>) -> Self {
+    BlockTemplate { id: id.into(), previous_hash: previous_hash.into(), timestamp: at.timestamp(), data, difficulty: Difficulty::default() }.mine()
+  }
+
+  /// Returns the block's timestamp as a date and time.
+  /// Timestamps outside the range chrono can represent saturate to its earliest or latest date.
+  /// 
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::block::Block;
+  /// let block = Block::new(1, "genesis", "new".to_string());
+  /// assert_eq!(block.datetime().timestamp(), block.timestamp);
+  /// ```
+  pub fn datetime(&self) -> DateTime<Utc> {
+    timestamp::to_datetime(self.timestamp)
+  }
+
+  /// Serializes the block to JSON, writing the timestamp in the given format.
+  /// Either format deserializes back into the same block; see [`TimestampFormat::Rfc3339`] for its range.
+  /// 
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::block::Block;
+  /// # use simple_blockchain::timestamp::TimestampFormat;
+  /// let block = Block::new(1, "genesis", "new".to_string());
+  /// let json = block.to_json(TimestampFormat::Rfc3339);
+  /// assert!(json.contains(&format!(r#""timestamp":"{}""#, block.datetime().format("%Y-%m-%dT%H:%M:%SZ"))));
+  /// assert_eq!(serde_json::from_str::<Block>(&json).unwrap(), block);
+  /// ```
+  pub fn to_json(&self, format: TimestampFormat) -> String {
+    match format {
+      TimestampFormat::Unix => serde_json::to_string(self).unwrap(),
+      TimestampFormat::Rfc3339 => {
+        let mut value = serde_json::to_value(self).unwrap();
+        if let Some(text) = timestamp::to_exact_rfc3339(self.timestamp) { value["timestamp"] = text.into() };
+        value.to_string()
+      }
+    }
+  }
+
+  /// Returns the exact bytes that are hashed to produce the block's hash.
+  /// 
+  /// The preimage is compact JSON with the keys in alphabetical order, and the data as its [`BlockData::canonical`] value.
+  /// Implementations in other languages must produce the same bytes to agree on hashes.
+  /// 
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::block::Block;
+  /// # use simple_blockchain::types::BlockId;
+  /// let block = Block {
+  ///   id: BlockId(1),
+  ///   hash: "".into(),
+  ///   previous_hash: "genesis".into(),
+  ///   timestamp: 1643223669,
+  ///   data: "next".to_string(),
+  ///   nonce: 7
+  /// };
+  /// assert_eq!(
+  ///   block.hash_preimage(),
+  ///   br#"{"data":"next","id":1,"nonce":7,"previous_hash":"genesis","timestamp":1643223669}"#
+  /// );
+  /// ```
+  pub fn hash_preimage(&self) -> Vec<u8> {
+    hash_preimage(self.id.0, self.timestamp, &self.previous_hash, &self.data.canonical(), self.nonce)
+  }
+
+  /// Returns the size of the block in bytes, counted as the length of its hash preimage.
+  /// 
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::block::Block;
+  /// let block = Block::new(1, "genesis", "next".to_string());
+  /// assert_eq!(block.size(), block.hash_preimage().len());
+  /// ```
+  pub fn size(&self) -> usize {
+    self.hash_preimage().len()
   }
 }
 
@@ -40,7 +185,7 @@ impl Block {
 fn creates_a_new_block() {
   let block = Block::new(
     69,
-    &"0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".to_string(),
+    "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43",
     "foo".to_string()
   );
   assert_eq!(block.id, 69);
@@ -50,3 +195,61 @@ fn creates_a_new_block() {
   assert_eq!(block.data, "foo".to_string());
   assert!(block.nonce > 0);
 }
+
+#[test]
+fn preimage_hashes_to_block_hash() {
+  use sha2::{ Sha256, Digest };
+  let block = Block::new(1, "genesis", "foo".to_string());
+  assert_eq!(hex::encode(Sha256::digest(&block.hash_preimage())), block.hash);
+}
+
+#[test]
+fn writes_timestamps_in_either_format() {
+  let block = Block {
+    id: BlockId(1),
+    hash: "00005ea81511a2a24a25a2055d5fc581879b8cfbedc5ddfb6918caed4917138e".into(),
+    previous_hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
+    timestamp: 1643223669,
+    data: String::from("next"),
+    nonce: 24271,
+  };
+  assert_eq!(block.to_json(TimestampFormat::Unix), serde_json::to_string(&block).unwrap());
+  let rfc3339 = block.to_json(TimestampFormat::Rfc3339);
+  assert!(rfc3339.contains(r#""timestamp":"2022-01-26T19:01:09Z""#));
+  assert_eq!(serde_json::from_str::<Block>(&rfc3339).unwrap(), block);
+}
+
+#[test]
+fn round_trips_timestamps_rfc3339_cant_express() {
+  for timestamp in [i64::MAX, i64::MIN, 253402300800, -62167219201] {
+    let block = Block { id: BlockId(1), hash: "".into(), previous_hash: "genesis".into(), timestamp, data: String::from("next"), nonce: 0 };
+    let json = block.to_json(TimestampFormat::Rfc3339);
+    assert!(json.contains(&format!(r#""timestamp":{}"#, timestamp)), "{}", json);
+    assert_eq!(serde_json::from_str::<Block>(&json).unwrap(), block);
+  }
+}
+
+#[test]
+fn reads_rfc3339_timestamps_with_offsets() {
+  let json = r#"{"id":1,"hash":"","previous_hash":"genesis","timestamp":"2022-01-26T14:01:09-05:00","data":"next","nonce":0}"#;
+  let block: Block = serde_json::from_str(json).unwrap();
+  assert_eq!(block.timestamp, 1643223669);
+}
+
+#[test]
+fn hashes_structured_data_by_its_canonical_value() {
+  #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+  struct Transfer { to: String, amount: u64 }
+  impl BlockData for Transfer {}
+
+  fn at<T>(data: T) -> Block<T> {
+    Block { id: BlockId(1), hash: "".into(), previous_hash: "genesis".into(), timestamp: 1643223669, data, nonce: 7 }
+  }
+  let typed = at(Transfer { to: "bob".to_string(), amount: 5 });
+  let untyped = at(serde_json::json!({ "amount": 5, "to": "bob" }));
+  assert_eq!(
+    typed.hash_preimage(),
+    br#"{"data":{"amount":5,"to":"bob"},"id":1,"nonce":7,"previous_hash":"genesis","timestamp":1643223669}"#
+  );
+  assert_eq!(typed.hash_preimage(), untyped.hash_preimage());
+}
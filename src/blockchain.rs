@@ -1,23 +1,45 @@
-use crate::block::Block;
-use crate::helpers::*;
-use crate::error::{ BlockchainError, BlockchainError::* };
+use serde::{ Serialize, Deserialize };
+use crate::block::{ Block, BlockData, ChainBlock };
+use crate::decode::{ self, DecodeLimits };
+use crate::difficulty::Difficulty;
+use crate::params::ChainParams;
+use crate::rules::{ BlockSize, HashMatch, ProofOfWork, Rule };
+use crate::template::BlockTemplate;
+use crate::types::*;
+use chrono::Utc;
+use crate::error::{ BlockchainError, BlockchainError::*, BlockValidationError };
 
-#[derive(PartialEq, Debug)]
-pub struct Blockchain<Block> {
-  pub blocks: Vec<Block>
+/// A machine-readable snapshot of a blockchain's state, from [`Blockchain::summary`].
+/// 
+/// The JSON field names and types are stable. Fields are `null` when the blockchain is empty.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ChainSummary {
+  pub height: Option<BlockHeight>,
+  pub tip_hash: Option<BlockHash>,
+  pub work: u128,
+  pub difficulty: Difficulty,
+  pub last_timestamp: Option<i64>,
+  pub valid: bool,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Blockchain<Block: ChainBlock> {
+  pub blocks: Vec<Block>,
+  pub params: ChainParams<Block::Data>
 }
 
 impl Blockchain<Block> {
-  /// Creates a new, empty blockchain.
+  /// Creates a new, empty blockchain with the default parameters.
   /// 
   /// # Examples
   /// ```
   /// # use simple_blockchain::block::Block;
   /// # use simple_blockchain::blockchain::Blockchain;
-  /// assert_eq!(Blockchain::new(), Blockchain::<Block> { blocks: vec![] });
+  /// # use simple_blockchain::params::ChainParams;
+  /// assert_eq!(Blockchain::new(), Blockchain::<Block> { blocks: vec![], params: ChainParams::default() });
   /// ```
   pub fn new() -> Self {
-    Self { blocks: vec![] }
+    Self::with_params(ChainParams::default())
   }
 
   /// Initializes the blockchain with a genesis block.
@@ -42,24 +64,182 @@ impl Blockchain<Block> {
   /// assert_eq!(my_blockchain.genesis(), Err(BlockchainError::InvalidChainLength));
   /// ```
   pub fn genesis(&mut self)  -> Result<(), BlockchainError> {
-    if self.blocks.len() > 0 { return Err(InvalidChainLength) };
-    let genesis_block = Block::new(0, &"genesis".to_string(), "genesis!".to_string());
+    self.genesis_with("genesis!".to_string())
+  }
+
+}
+
+impl<T: BlockData> Blockchain<Block<T>> {
+  /// Creates a new, empty blockchain that validates blocks against `params`.
+  /// Its blocks carry the same type of data as the params' rules check.
+  /// 
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::block::Block;
+  /// # use simple_blockchain::blockchain::Blockchain;
+  /// # use simple_blockchain::difficulty::Difficulty;
+  /// # use simple_blockchain::params::ChainParams;
+  /// let my_blockchain = Blockchain::<Block>::with_params(ChainParams { difficulty: Difficulty::DEMO, ..Default::default() });
+  /// assert_eq!(my_blockchain.params.difficulty, Difficulty::DEMO);
+  /// ```
+  pub fn with_params(params: ChainParams<T>) -> Self {
+    Self { blocks: vec![], params }
+  }
+
+  /// Initializes the blockchain with a genesis block carrying `data`.
+  /// 
+  /// # Examples
+  /// ```
+  /// # use serde_json::json;
+  /// # use simple_blockchain::blockchain::Blockchain;
+  /// # use simple_blockchain::difficulty::Difficulty;
+  /// # use simple_blockchain::params::ChainParams;
+  /// let mut my_blockchain = Blockchain::with_params(ChainParams { difficulty: Difficulty::DEMO, ..Default::default() });
+  /// assert_eq!(my_blockchain.genesis_with(json!({ "supply": 100 })), Ok(()));
+  /// assert_eq!(my_blockchain.blocks[0].data["supply"], 100);
+  /// ```
+  /// 
+  /// # Errors
+  /// Returns [`BlockchainError::InvalidChainLength`] if the blockchain is not empty,
+  /// and [`BlockchainError::InvalidBlock`] if the genesis block fails [`Blockchain::validate_genesis`].
+  pub fn genesis_with(&mut self, data: T) -> Result<(), BlockchainError> {
+    if !self.blocks.is_empty() { return Err(InvalidChainLength) };
+    let template = BlockTemplate {
+      id: BlockId(0),
+      previous_hash: "genesis".into(),
+      timestamp: Utc::now().timestamp(),
+      data,
+      difficulty: self.params.at_height(BlockHeight(0)).difficulty
+    };
+    let genesis_block = template.mine();
+    self.validate_genesis(&genesis_block).map_err(InvalidBlock)?;
     self.blocks.push(genesis_block);
+    self.debug_check_invariants();
     Ok(())
   }
 
-  fn is_block_valid(&self, block: &Block, previous_block: &Block) -> bool {
-    let hash = calculate_hash(block.id, block.timestamp, &block.previous_hash, &block.data, block.nonce);
-    if block.hash != hash {
-      return false;
-    } else if block.previous_hash != previous_block.hash {
-      return false;
-    } else if !binary_string_of(&block.hash).starts_with(PREFIX) {
-      return false;
-    } else if block.id != (previous_block.id + 1) {
-      return false;
+  /// Returns the height of the last block, or `None` if the blockchain is empty.
+  /// 
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::blockchain::Blockchain;
+  /// # use simple_blockchain::types::BlockHeight;
+  /// let mut my_blockchain = Blockchain::new();
+  /// assert_eq!(my_blockchain.height(), None);
+  /// my_blockchain.genesis();
+  /// assert_eq!(my_blockchain.height(), Some(BlockHeight(0)));
+  /// ```
+  pub fn height(&self) -> Option<BlockHeight> {
+    self.blocks.len().checked_sub(1).map(|height| BlockHeight(height as u64))
+  }
+
+  /// Returns the block at `height`, if there is one.
+  /// 
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::blockchain::Blockchain;
+  /// # use simple_blockchain::types::BlockHeight;
+  /// let mut my_blockchain = Blockchain::new();
+  /// my_blockchain.genesis();
+  /// assert_eq!(my_blockchain.block_at(BlockHeight(0)), my_blockchain.blocks.first());
+  /// assert_eq!(my_blockchain.block_at(BlockHeight(1)), None);
+  /// ```
+  pub fn block_at(&self, height: BlockHeight) -> Option<&Block<T>> {
+    usize::try_from(height.0).ok().and_then(|index| self.blocks.get(index))
+  }
+
+  /// Returns the total work in the blockchain: the expected number of hashes it took to mine every block,
+  /// at the difficulty in force at its height.
+  /// 
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::blockchain::Blockchain;
+  /// let mut my_blockchain = Blockchain::new();
+  /// assert_eq!(my_blockchain.work(), 0);
+  /// my_blockchain.genesis();
+  /// assert_eq!(my_blockchain.work(), 65536);
+  /// ```
+  pub fn work(&self) -> u128 {
+    (0..self.blocks.len() as u64)
+      .map(|height| self.params.at_height(BlockHeight(height)).difficulty.work())
+      .fold(0, u128::saturating_add)
+  }
+
+  /// Summarizes the blockchain's height, tip, work, difficulty for the next block, last timestamp and validity.
+  /// 
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::blockchain::Blockchain;
+  /// # use simple_blockchain::types::BlockHeight;
+  /// let mut my_blockchain = Blockchain::new();
+  /// my_blockchain.genesis();
+  /// let summary = my_blockchain.summary();
+  /// assert_eq!(summary.height, Some(BlockHeight(0)));
+  /// assert_eq!(summary.tip_hash.as_ref(), Some(&my_blockchain.blocks[0].hash));
+  /// assert!(!summary.valid);
+  /// ```
+  pub fn summary(&self) -> ChainSummary {
+    let tip = self.blocks.last();
+    ChainSummary {
+      height: self.height(),
+      tip_hash: tip.map(|block| block.hash.clone()),
+      work: self.work(),
+      difficulty: self.params.at_height(BlockHeight(self.blocks.len() as u64)).difficulty,
+      last_timestamp: tip.map(|block| block.timestamp),
+      valid: self.is_chain_valid()
     }
-    true
+  }
+
+  /// Returns [`Blockchain::summary`] as compact JSON, for scripts and monitoring checks.
+  /// 
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::blockchain::Blockchain;
+  /// let my_blockchain = Blockchain::new();
+  /// assert_eq!(
+  ///   my_blockchain.summary_json(),
+  ///   r#"{"height":null,"tip_hash":null,"work":0,"difficulty":16,"last_timestamp":null,"valid":false}"#
+  /// );
+  /// ```
+  pub fn summary_json(&self) -> String {
+    serde_json::to_string(&self.summary()).unwrap()
+  }
+
+  /// Checks `block` against every rule in the blockchain's parameters at its height, as if it were added after `previous_block`.
+  /// 
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::block::Block;
+  /// # use simple_blockchain::blockchain::Blockchain;
+  /// # use simple_blockchain::error::BlockValidationError;
+  /// let mut my_blockchain = Blockchain::new();
+  /// my_blockchain.genesis();
+  /// let genesis = &my_blockchain.blocks[0];
+  /// let block = Block::new(genesis.id + 1, &genesis.hash, "next".to_string());
+  /// assert_eq!(my_blockchain.validate_block(&block, genesis), Ok(()));
+  /// let unlinked = Block::new(genesis.id + 1, "elsewhere", "next".to_string());
+  /// assert_eq!(my_blockchain.validate_block(&unlinked, genesis), Err(BlockValidationError::PreviousHashMismatch));
+  /// ```
+  /// 
+  /// # Errors
+  /// Returns the [`BlockValidationError`] of the first rule that fails.
+  pub fn validate_block(&self, block: &Block<T>, previous_block: &Block<T>) -> Result<(), BlockValidationError> {
+    let params = self.params.at_height(BlockHeight(block.id.0));
+    params.rules.iter().try_for_each(|rule| rule.check(block, previous_block, &params))
+  }
+
+  /// Checks `genesis` against every rule's [`Rule::check_genesis`] in the blockchain's parameters at height 0.
+  /// Only rules that say something about the first block, like [`Validators`](crate::crypto::Validators), check anything.
+  /// 
+  /// # Errors
+  /// Returns the [`BlockValidationError`] of the first rule that fails.
+  pub fn validate_genesis(&self, genesis: &Block<T>) -> Result<(), BlockValidationError> {
+    let params = self.params.at_height(BlockHeight(0));
+    params.rules.iter().try_for_each(|rule| rule.check_genesis(genesis, &params))
+  }
+
+  fn is_block_valid(&self, block: &Block<T>, previous_block: &Block<T>) -> bool {
+    self.validate_block(block, previous_block).is_ok()
   }
 
   /// Adds a valid block to the chain.
@@ -82,10 +262,11 @@ impl Blockchain<Block> {
   /// 
   /// # Errors
   /// Returns [`BlockchainError`] if blockchain is empty or block is invalid.
+  /// An invalid block's error says which rule it broke.
   /// ```
   /// # use simple_blockchain::block::Block;
   /// # use simple_blockchain::blockchain::Blockchain;
-  /// # use simple_blockchain::error::BlockchainError;
+  /// # use simple_blockchain::error::{ BlockchainError, BlockValidationError };
   /// let mut my_blockchain = Blockchain::new();
   /// let next_block = Block::new(1, &"hash".to_string(), "data".to_string());
   /// 
@@ -99,22 +280,82 @@ impl Blockchain<Block> {
   ///     "next".to_string()
   ///   );
   /// 
-  ///   assert_eq!(my_blockchain.add_block(next_block), Err(BlockchainError::InvalidBlock));
+  ///   assert_eq!(
+  ///     my_blockchain.add_block(next_block),
+  ///     Err(BlockchainError::InvalidBlock(BlockValidationError::PreviousHashMismatch))
+  ///   );
   /// }
   /// ```
-  pub fn add_block(&mut self, block: Block) -> Result<(), BlockchainError> {
-    match &self.blocks.last() {
-      Some(tail) => if self.is_block_valid(&block, tail) {
+  pub fn add_block(&mut self, block: Block<T>) -> Result<(), BlockchainError> {
+    match self.blocks.last() {
+      Some(tail) => {
+        self.validate_block(&block, tail)?;
         self.blocks.push(block);
+        self.debug_check_invariants();
         Ok(())
-      } else {
-        Err(InvalidBlock)
       },
       None => Err(InvalidChainLength)
     }
   }
 
-  /// Returns `true` if all blocks in the blockchain are valid.
+  /// Prepares a template for the next block, to be mined elsewhere.
+  /// 
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::blockchain::Blockchain;
+  /// # let mut my_blockchain = Blockchain::new();
+  /// # my_blockchain.genesis();
+  /// let template = my_blockchain.block_template("next".to_string()).unwrap();
+  /// assert_eq!(template.id, 1);
+  /// assert_eq!(template.previous_hash, my_blockchain.blocks[0].hash);
+  /// ```
+  /// 
+  /// # Errors
+  /// Returns [`BlockchainError::InvalidChainLength`] if the blockchain is empty,
+  /// and [`BlockValidationError::IdOutOfSequence`] if the tail already has the last possible id.
+  /// ```
+  /// # use simple_blockchain::blockchain::Blockchain;
+  /// # use simple_blockchain::error::BlockchainError;
+  /// let my_blockchain = Blockchain::new();
+  /// assert_eq!(my_blockchain.block_template("next".to_string()), Err(BlockchainError::InvalidChainLength));
+  /// ```
+  pub fn block_template(&self, data: T) -> Result<BlockTemplate<T>, BlockchainError> {
+    let tail = self.blocks.last().ok_or(InvalidChainLength)?;
+    let id = tail.id.checked_add(1).ok_or(InvalidBlock(BlockValidationError::IdOutOfSequence))?;
+    Ok(BlockTemplate {
+      id,
+      previous_hash: tail.hash.clone(),
+      timestamp: Utc::now().timestamp(),
+      data,
+      difficulty: self.params.at_height(BlockHeight(id.0)).difficulty
+    })
+  }
+
+  /// Adds a block mined from a template, given as the mined block's JSON.
+  /// 
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::blockchain::Blockchain;
+  /// # let mut my_blockchain = Blockchain::new();
+  /// # my_blockchain.genesis();
+  /// let template = my_blockchain.block_template("next".to_string()).unwrap();
+  /// let mined = serde_json::to_string(&template.mine()).unwrap();
+  /// assert_eq!(my_blockchain.submit_mined(&mined), Ok(()));
+  /// assert_eq!(my_blockchain.blocks[1].data, "next");
+  /// ```
+  /// 
+  /// # Errors
+  /// Returns [`BlockchainError::InputTooLarge`] or [`BlockchainError::InputTooDeep`] if the input is over
+  /// the [`DecodeLimits::for_block`] of the next block's size limit,
+  /// [`BlockchainError::MalformedJson`] if it is not a block,
+  /// otherwise the same errors as [`Blockchain::add_block`].
+  pub fn submit_mined(&mut self, mined: &str) -> Result<(), BlockchainError> {
+    let max_block_size = self.params.at_height(BlockHeight(self.blocks.len() as u64)).max_block_size;
+    let block: Block<T> = decode::from_json(mined, DecodeLimits::for_block(max_block_size))?;
+    self.add_block(block)
+  }
+
+  /// Returns `true` if all blocks in the blockchain are valid, the genesis block under [`Blockchain::validate_genesis`].
   /// Returns `false` otherwise, including if no blocks beyond genesis have been added.
   /// 
   /// # Examples
@@ -135,16 +376,21 @@ impl Blockchain<Block> {
   /// }
   /// ```
   pub fn is_chain_valid(&self) -> bool {
-    if self.blocks.len() <= 1 { return false };
+    self.are_blocks_valid(&self.blocks)
+  }
+
+  fn are_blocks_valid(&self, blocks: &[Block<T>]) -> bool {
+    if blocks.len() <= 1 || self.validate_genesis(&blocks[0]).is_err() { return false };
 
-    let mut blocks = self.blocks.iter();
+    let mut previous_blocks = blocks.iter();
+    let mut blocks = blocks.iter();
     blocks.next();
-    let mut previous_blocks = self.blocks.iter();
 
-    blocks.all(|block| self.is_block_valid(&block, &previous_blocks.next().unwrap()))
+    blocks.all(|block| self.is_block_valid(block, previous_blocks.next().unwrap()))
   }
 
   /// Chooses the longest chain between itself and a remote blockchain.
+  /// The remote blocks are validated against this blockchain's parameters, not the remote's.
   /// 
   /// Examples
   /// ```
@@ -173,33 +419,154 @@ impl Blockchain<Block> {
   /// assert!(remote_chain.blocks.len() == 3);
   /// local_chain.choose_chain(&remote_chain);
   /// assert!(local_chain.blocks.len() == 3);
-  pub fn choose_chain(&mut self, remote: &Blockchain<Block>) {
+  pub fn choose_chain(&mut self, remote: &Blockchain<Block<T>>) {
     let is_local_valid = self.is_chain_valid();
-    let is_remote_valid = remote.is_chain_valid();
+    let is_remote_valid = self.are_blocks_valid(&remote.blocks);
 
     if is_local_valid
     && is_remote_valid
     && remote.blocks.len() > self.blocks.len() {
       self.blocks = remote.blocks.clone();
+      self.debug_check_invariants();
     }
     
     if is_remote_valid
     && !is_local_valid {
       self.blocks = remote.blocks.clone();
+      self.debug_check_invariants();
     }
   }
+
+  // With the debug-invariants feature, panics unless the genesis block is valid and every later block is valid
+  // after the one before it, under the params at its height. Revalidates the whole chain, so it's only for debugging.
+  pub(crate) fn debug_check_invariants(&self) {
+    #[cfg(feature = "debug-invariants")]
+    {
+      let genesis = self.blocks.first().and_then(|genesis| self.validate_genesis(genesis).err().map(|error| {
+        format!("genesis block {} ({}): {}", genesis.id, genesis.hash, error)
+      }));
+      let violations = genesis.into_iter().chain(self.blocks.windows(2)
+        .filter_map(|pair| self.validate_block(&pair[1], &pair[0]).err().map(|error| {
+          format!("block {} ({}) after {}: {}", pair[1].id, pair[1].hash, pair[0].hash, error)
+        })))
+        .collect::<Vec<_>>();
+      assert!(violations.is_empty(), "blockchain invariants broken:\n{}", violations.join("\n"));
+    }
+  }
+}
+
+/// Checks that a block's claimed hash looks like a mined hash under `params` at the block's height, without recomputing it.
+/// 
+/// This needs no chain and does no hashing, so it can be used to drop spam before doing any real work.
+/// Passing doesn't make a block valid; [`Blockchain::add_block`] still checks everything.
+/// To drop spam before the block's data is even decoded, use [`quick_check_json`].
+/// 
+/// # Examples
+/// ```
+/// # use simple_blockchain::block::Block;
+/// # use simple_blockchain::blockchain::quick_check;
+/// # use simple_blockchain::difficulty::Difficulty;
+/// # use simple_blockchain::params::ChainParams;
+/// let params = ChainParams::default();
+/// let block = Block::new(1, "genesis", "next".to_string());
+/// assert!(quick_check(&block, &params));
+/// let spam = Block { hash: "ff".into(), ..block };
+/// assert!(!quick_check(&spam, &params));
+/// ```
+pub fn quick_check<T: BlockData>(block: &Block<T>, params: &ChainParams<T>) -> bool {
+  claims_valid_hash(block.id, &block.hash, params)
+}
+
+/// Like [`quick_check`], but on a block's JSON: checks its length against the size limit, and decodes only its id
+/// and hash. The data is skipped over, not decoded, so junk is dropped before any payload is built.
+/// 
+/// # Examples
+/// ```
+/// # use simple_blockchain::block::Block;
+/// # use simple_blockchain::blockchain::quick_check_json;
+/// # use simple_blockchain::params::ChainParams;
+/// let params: ChainParams = ChainParams::default();
+/// let block = Block::new(1, "genesis", "next".to_string());
+/// assert!(quick_check_json(&serde_json::to_string(&block).unwrap(), &params));
+/// assert!(!quick_check_json(r#"{"id":1,"hash":"ff","data":"spam"}"#, &params));
+/// assert!(!quick_check_json("not a block", &params));
+/// ```
+pub fn quick_check_json<T: BlockData>(json: &str, params: &ChainParams<T>) -> bool {
+  let largest = params.upgrades.iter().filter_map(|upgrade| upgrade.max_block_size).fold(params.max_block_size, usize::max);
+  let Ok(header) = decode::from_json::<BlockHeader>(json, DecodeLimits::for_block(largest)) else { return false };
+  let max_bytes = DecodeLimits::for_block(params.at_height(BlockHeight(header.id.0)).max_block_size).max_bytes;
+  json.len() <= max_bytes && claims_valid_hash(header.id, &header.hash, params)
+}
+
+// The fields quick_check_json reads; anything else in the block, like its data, is skipped by serde.
+#[derive(Deserialize)]
+struct BlockHeader {
+  id: BlockId,
+  hash: BlockHash,
+}
+
+fn claims_valid_hash<T: BlockData>(id: BlockId, hash: &BlockHash, params: &ChainParams<T>) -> bool {
+  hash.len() == 64 && params.at_height(BlockHeight(id.0)).difficulty.is_met_by(hash)
+}
+
+/// Returns `true` if a block's hash is the hash of its contents and meets `difficulty`.
+/// 
+/// Unlike [`quick_check`], this recomputes the hash, so a passing block really was mined.
+/// 
+/// # Examples
+/// ```
+/// # use simple_blockchain::block::Block;
+/// # use simple_blockchain::blockchain::verify_pow;
+/// # use simple_blockchain::difficulty::Difficulty;
+/// let block = Block::new(1, "genesis", "next".to_string());
+/// assert!(verify_pow(&block, Difficulty::MEDIUM));
+/// assert!(!verify_pow(&Block { data: "forged".to_string(), ..block }, Difficulty::MEDIUM));
+/// ```
+pub fn verify_pow<T: BlockData>(block: &Block<T>, difficulty: Difficulty) -> bool {
+  let params = ChainParams::<T> { difficulty, ..Default::default() };
+  ProofOfWork.check(block, block, &params).is_ok() && HashMatch.check(block, block, &params).is_ok()
+}
+
+/// Checks everything about a block that doesn't depend on the block before it:
+/// its size, proof of work and hash, under `params` at its height.
+/// 
+/// # Examples
+/// ```
+/// # use simple_blockchain::block::Block;
+/// # use simple_blockchain::blockchain::verify_block_standalone;
+/// # use simple_blockchain::error::BlockValidationError;
+/// # use simple_blockchain::params::ChainParams;
+/// let block = Block::new(1, "genesis", "next".to_string());
+/// assert_eq!(verify_block_standalone(&block, &ChainParams::default()), Ok(()));
+/// let small = ChainParams { max_block_size: 16, ..Default::default() };
+/// assert_eq!(verify_block_standalone(&block, &small), Err(BlockValidationError::TooLarge));
+/// ```
+/// 
+/// # Errors
+/// Returns the [`BlockValidationError`] of the first check that fails.
+pub fn verify_block_standalone<T: BlockData>(block: &Block<T>, params: &ChainParams<T>) -> Result<(), BlockValidationError> {
+  let params = params.at_height(BlockHeight(block.id.0));
+  BlockSize.check(block, block, &params)?;
+  ProofOfWork.check(block, block, &params)?;
+  HashMatch.check(block, block, &params)
+}
+
+impl Default for Blockchain<Block> {
+  fn default() -> Self {
+    Self::new()
+  }
 }
 
 #[test]
 fn creates_a_new_app() {
-  let expected = Blockchain::<Block> { blocks: vec![] };
+  let expected = Blockchain::<Block> { blocks: vec![], params: ChainParams::default() };
   let result = Blockchain::new();
   assert_eq!(expected, result);
 }
 
 #[test]
 fn creates_genesis_block() {
-  let mut new_app = Blockchain::<Block> { blocks: vec![] };
+  let mut new_app = Blockchain::<Block> { blocks: vec![], params: ChainParams::default() };
   let result = new_app.genesis();
   assert!(result.is_ok());
   assert!(new_app.blocks.len() == 1);
@@ -207,7 +574,7 @@ fn creates_genesis_block() {
 
 #[test]
 fn cant_genesis_more_than_once() {
-  let mut new_app = Blockchain::<Block> { blocks: vec![] };
+  let mut new_app = Blockchain::<Block> { blocks: vec![], params: ChainParams::default() };
   let first_result = new_app.genesis();
   assert!(first_result.is_ok());
   let mut again = new_app;
@@ -217,20 +584,20 @@ fn cant_genesis_more_than_once() {
 
 #[test]
 fn adds_a_valid_block() {
-  let mut new_app = Blockchain::<Block> { blocks: vec![] };
+  let mut new_app = Blockchain::<Block> { blocks: vec![], params: ChainParams::default() };
   let genesis_block = Block {
-    id: 0,
-    hash: "0000dbeb9e573d5382c63fd9a222c3720a4341b06416348fc5bbc0d19380a248".to_string(),
-    previous_hash: "genesis".to_string(),
+    id: BlockId(0),
+    hash: "0000dbeb9e573d5382c63fd9a222c3720a4341b06416348fc5bbc0d19380a248".into(),
+    previous_hash: "genesis".into(),
     timestamp: 1643223000,
     data: "genesis!".to_string(),
     nonce: 44475
   };
   new_app.blocks.push(genesis_block);
   let block = Block {
-    id: 1,
-    hash: "0000cc07887fb749c99974e8e93debb64e205086f6d0962ef17bf6f0bb295f3e".to_string(),
-    previous_hash: "0000dbeb9e573d5382c63fd9a222c3720a4341b06416348fc5bbc0d19380a248".to_string(),
+    id: BlockId(1),
+    hash: "0000cc07887fb749c99974e8e93debb64e205086f6d0962ef17bf6f0bb295f3e".into(),
+    previous_hash: "0000dbeb9e573d5382c63fd9a222c3720a4341b06416348fc5bbc0d19380a248".into(),
     timestamp: 1643223669,
     data: String::from("next"),
     nonce: 236492,
@@ -243,20 +610,20 @@ fn adds_a_valid_block() {
 
 #[test]
 fn errs_when_adding_invalid_block() {
-  let mut new_app = Blockchain::<Block> { blocks: vec![] };
+  let mut new_app = Blockchain::<Block> { blocks: vec![], params: ChainParams::default() };
   let genesis_block = Block {
-    id: 0,
-    hash: "0000dbeb9e573d5382c63fd9a222c3720a4341b06416348fc5bbc0d19380a248".to_string(),
-    previous_hash: "genesis".to_string(),
+    id: BlockId(0),
+    hash: "0000dbeb9e573d5382c63fd9a222c3720a4341b06416348fc5bbc0d19380a248".into(),
+    previous_hash: "genesis".into(),
     timestamp: 1643223000,
     data: "genesis!".to_string(),
     nonce: 44475
   };
   new_app.blocks.push(genesis_block);
   let invalid_block = Block {
-    id: 1,
-    hash: String::from("0000ff"),
-    previous_hash: "not_the_previous_hash".to_string(),
+    id: BlockId(1),
+    hash: "0000ff".into(),
+    previous_hash: "not_the_previous_hash".into(),
     timestamp: 1643223669,
     data: String::from("next"),
     nonce: 2836,
@@ -267,19 +634,19 @@ fn errs_when_adding_invalid_block() {
 
 #[test]
 fn valid_when_prev_hash_match() {
-  let new_app = Blockchain::<Block> { blocks: vec![] };
+  let new_app = Blockchain::<Block> { blocks: vec![], params: ChainParams::default() };
   let block = Block {
-    id: 1,
-    hash: "00005ea81511a2a24a25a2055d5fc581879b8cfbedc5ddfb6918caed4917138e".to_string(),
-    previous_hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".to_string(),
+    id: BlockId(1),
+    hash: "00005ea81511a2a24a25a2055d5fc581879b8cfbedc5ddfb6918caed4917138e".into(),
+    previous_hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
     timestamp: 1643223669,
     data: String::from("next"),
     nonce: 24271,
   };
   let previous_block = Block {
-    id: 0,
-    hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".to_string(),
-    previous_hash: String::from("genesis"),
+    id: BlockId(0),
+    hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
+    previous_hash: "genesis".into(),
     timestamp: 1643223669,
     data: String::from("genesis!"),
     nonce: 2836,
@@ -289,19 +656,19 @@ fn valid_when_prev_hash_match() {
 
 #[test]
 fn invalid_when_prev_hash_mismatch() {
-  let new_app = Blockchain::<Block> { blocks: vec![] };
+  let new_app = Blockchain::<Block> { blocks: vec![], params: ChainParams::default() };
   let block = Block {
-    id: 1,
-    hash: String::from("0000ff"),
-    previous_hash: "not_the_previous_hash".to_string(),
+    id: BlockId(1),
+    hash: "0000ff".into(),
+    previous_hash: "not_the_previous_hash".into(),
     timestamp: 1643223669,
     data: String::from("next"),
     nonce: 2836,
   };
   let previous_block = Block {
-    id: 0,
-    hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".to_string(),
-    previous_hash: String::from("genesis"),
+    id: BlockId(0),
+    hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
+    previous_hash: "genesis".into(),
     timestamp: 1643223669,
     data: String::from("genesis!"),
     nonce: 2836,
@@ -311,19 +678,19 @@ fn invalid_when_prev_hash_mismatch() {
 
 #[test]
 fn valid_when_prefix_match() {
-  let new_app = Blockchain::<Block> { blocks: vec![] };
+  let new_app = Blockchain::<Block> { blocks: vec![], params: ChainParams::default() };
   let block = Block {
-    id: 1,
-    hash: "00005ea81511a2a24a25a2055d5fc581879b8cfbedc5ddfb6918caed4917138e".to_string(),
-    previous_hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".to_string(),
+    id: BlockId(1),
+    hash: "00005ea81511a2a24a25a2055d5fc581879b8cfbedc5ddfb6918caed4917138e".into(),
+    previous_hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
     timestamp: 1643223669,
     data: String::from("next"),
     nonce: 24271,
   };
   let previous_block = Block {
-    id: 0,
-    hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".to_string(),
-    previous_hash: String::from("genesis"),
+    id: BlockId(0),
+    hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
+    previous_hash: "genesis".into(),
     timestamp: 1643223669,
     data: String::from("genesis!"),
     nonce: 2836,
@@ -333,19 +700,19 @@ fn valid_when_prefix_match() {
 
 #[test]
 fn invalid_when_prefix_mismatch() {
-  let new_app = Blockchain::<Block> { blocks: vec![] };
+  let new_app = Blockchain::<Block> { blocks: vec![], params: ChainParams::default() };
   let block = Block {
-    id: 1,
-    hash: String::from("ff"),
-    previous_hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".to_string(),
+    id: BlockId(1),
+    hash: "ff".into(),
+    previous_hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
     timestamp: 1643223669,
     data: String::from("next"),
     nonce: 2836,
   };
   let previous_block = Block {
-    id: 0,
-    hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".to_string(),
-    previous_hash: String::from("genesis"),
+    id: BlockId(0),
+    hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
+    previous_hash: "genesis".into(),
     timestamp: 1643223669,
     data: String::from("genesis!"),
     nonce: 2836,
@@ -355,19 +722,19 @@ fn invalid_when_prefix_mismatch() {
 
 #[test]
 fn valid_when_next_id() {
-  let new_app = Blockchain::<Block> { blocks: vec![] };
+  let new_app = Blockchain::<Block> { blocks: vec![], params: ChainParams::default() };
   let block = Block {
-    id: 1,
-    hash: "00005ea81511a2a24a25a2055d5fc581879b8cfbedc5ddfb6918caed4917138e".to_string(),
-    previous_hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".to_string(),
+    id: BlockId(1),
+    hash: "00005ea81511a2a24a25a2055d5fc581879b8cfbedc5ddfb6918caed4917138e".into(),
+    previous_hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
     timestamp: 1643223669,
     data: String::from("next"),
     nonce: 24271,
   };
   let previous_block = Block {
-    id: 0,
-    hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".to_string(),
-    previous_hash: String::from("genesis"),
+    id: BlockId(0),
+    hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
+    previous_hash: "genesis".into(),
     timestamp: 1643223669,
     data: String::from("genesis!"),
     nonce: 2836,
@@ -377,19 +744,19 @@ fn valid_when_next_id() {
 
 #[test]
 fn invalid_when_not_next_id() {
-  let new_app = Blockchain::<Block> { blocks: vec![] };
+  let new_app = Blockchain::<Block> { blocks: vec![], params: ChainParams::default() };
   let block = Block {
-    id: 2,
-    hash: String::from("0000ff"),
-    previous_hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".to_string(),
+    id: BlockId(2),
+    hash: "0000ff".into(),
+    previous_hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
     timestamp: 1643223669,
     data: String::from("next"),
     nonce: 2836,
   };
   let previous_block = Block {
-    id: 0,
-    hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".to_string(),
-    previous_hash: String::from("genesis"),
+    id: BlockId(0),
+    hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
+    previous_hash: "genesis".into(),
     timestamp: 1643223669,
     data: String::from("genesis!"),
     nonce: 2836,
@@ -399,19 +766,19 @@ fn invalid_when_not_next_id() {
 
 #[test]
 fn invalid_when_not_a_hash() {
-  let new_app = Blockchain::<Block> { blocks: vec![] };
+  let new_app = Blockchain::<Block> { blocks: vec![], params: ChainParams::default() };
   let block = Block {
-    id: 1,
-    hash: String::from("0000ff"),
-    previous_hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".to_string(),
+    id: BlockId(1),
+    hash: "0000ff".into(),
+    previous_hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
     timestamp: 1643223669,
     data: String::from("next"),
     nonce: 2836,
   };
   let previous_block = Block {
-    id: 0,
-    hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".to_string(),
-    previous_hash: String::from("genesis"),
+    id: BlockId(0),
+    hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
+    previous_hash: "genesis".into(),
     timestamp: 1643223669,
     data: String::from("genesis!"),
     nonce: 2836,
@@ -421,44 +788,44 @@ fn invalid_when_not_a_hash() {
 
 #[test]
 fn valid_chain_when_all_blocks_valid() {
-  let mut new_app = Blockchain::<Block> { blocks: vec![] };
+  let mut new_app = Blockchain::<Block> { blocks: vec![], params: ChainParams::default() };
   let genesis_block = Block {
-    id: 0,
-    hash: "0000dbeb9e573d5382c63fd9a222c3720a4341b06416348fc5bbc0d19380a248".to_string(),
-    previous_hash: "genesis".to_string(),
+    id: BlockId(0),
+    hash: "0000dbeb9e573d5382c63fd9a222c3720a4341b06416348fc5bbc0d19380a248".into(),
+    previous_hash: "genesis".into(),
     timestamp: 1643223000,
     data: "genesis!".to_string(),
     nonce: 44475
   };
   new_app.blocks.push(genesis_block);
   let first_block = Block {
-    id: 1,
-    hash: "0000cc07887fb749c99974e8e93debb64e205086f6d0962ef17bf6f0bb295f3e".to_string(),
-    previous_hash: "0000dbeb9e573d5382c63fd9a222c3720a4341b06416348fc5bbc0d19380a248".to_string(),
+    id: BlockId(1),
+    hash: "0000cc07887fb749c99974e8e93debb64e205086f6d0962ef17bf6f0bb295f3e".into(),
+    previous_hash: "0000dbeb9e573d5382c63fd9a222c3720a4341b06416348fc5bbc0d19380a248".into(),
     timestamp: 1643223669,
     data: String::from("next"),
     nonce: 236492,
   };
-  new_app.add_block(first_block);
+  assert!(new_app.add_block(first_block).is_ok());
   assert!(new_app.is_chain_valid());
 }
 
 #[test]
 fn invalid_chain_when_invalid_block() {
-  let mut new_app = Blockchain::<Block> { blocks: vec![] };
+  let mut new_app = Blockchain::<Block> { blocks: vec![], params: ChainParams::default() };
   let genesis_block = Block {
-    id: 0,
-    hash: "0000dbeb9e573d5382c63fd9a222c3720a4341b06416348fc5bbc0d19380a248".to_string(),
-    previous_hash: "genesis".to_string(),
+    id: BlockId(0),
+    hash: "0000dbeb9e573d5382c63fd9a222c3720a4341b06416348fc5bbc0d19380a248".into(),
+    previous_hash: "genesis".into(),
     timestamp: 1643223000,
     data: "genesis!".to_string(),
     nonce: 44475
   };
   new_app.blocks.push(genesis_block);
   let first_block = Block {
-    id: 1,
-    hash: String::from("0000ff"),
-    previous_hash: "not_the_previous_hash".to_string(),
+    id: BlockId(1),
+    hash: "0000ff".into(),
+    previous_hash: "not_the_previous_hash".into(),
     timestamp: 1643223669,
     data: String::from("next"),
     nonce: 2836,
@@ -469,12 +836,12 @@ fn invalid_chain_when_invalid_block() {
 
 #[test]
 fn chooses_the_longest_valid_chain() {
-  let mut app1 = Blockchain::<Block> { blocks: vec![] };
-  let mut app2 = Blockchain::<Block> { blocks: vec![] };
+  let mut app1 = Blockchain::<Block> { blocks: vec![], params: ChainParams::default() };
+  let mut app2 = Blockchain::<Block> { blocks: vec![], params: ChainParams::default() };
   let app1_genesis_block = Block {
-    id: 0,
-    hash: "0000dbeb9e573d5382c63fd9a222c3720a4341b06416348fc5bbc0d19380a248".to_string(),
-    previous_hash: "genesis".to_string(),
+    id: BlockId(0),
+    hash: "0000dbeb9e573d5382c63fd9a222c3720a4341b06416348fc5bbc0d19380a248".into(),
+    previous_hash: "genesis".into(),
     timestamp: 1643223000,
     data: "genesis!".to_string(),
     nonce: 44475
@@ -483,18 +850,18 @@ fn chooses_the_longest_valid_chain() {
   app1.blocks.push(app1_genesis_block);
   app2.blocks.push(app2_genesis_block);
   let app1_block = Block {
-    id: 1,
-    hash: "0000cc07887fb749c99974e8e93debb64e205086f6d0962ef17bf6f0bb295f3e".to_string(),
-    previous_hash: "0000dbeb9e573d5382c63fd9a222c3720a4341b06416348fc5bbc0d19380a248".to_string(),
+    id: BlockId(1),
+    hash: "0000cc07887fb749c99974e8e93debb64e205086f6d0962ef17bf6f0bb295f3e".into(),
+    previous_hash: "0000dbeb9e573d5382c63fd9a222c3720a4341b06416348fc5bbc0d19380a248".into(),
     timestamp: 1643223669,
     data: String::from("next"),
     nonce: 236492,
   };
   let app2_first_block = app1_block.clone();
   let app2_second_block = Block {
-    id: 2,
-    hash: "0000602c49108087d9878af09bb17b107eca531b635ab3f83d3381ddd5c9002b".to_string(),
-    previous_hash: "0000cc07887fb749c99974e8e93debb64e205086f6d0962ef17bf6f0bb295f3e".to_string(),
+    id: BlockId(2),
+    hash: "0000602c49108087d9878af09bb17b107eca531b635ab3f83d3381ddd5c9002b".into(),
+    previous_hash: "0000cc07887fb749c99974e8e93debb64e205086f6d0962ef17bf6f0bb295f3e".into(),
     timestamp: 1643224393,
     data: String::from("second"),
     nonce: 39308
@@ -505,3 +872,266 @@ fn chooses_the_longest_valid_chain() {
   app1.choose_chain(&app2);
   assert_eq!(app1.blocks, app2.blocks);
 }
+
+#[test]
+fn accepts_a_block_mined_from_its_template() {
+  let mut new_app = Blockchain::<Block> { blocks: vec![], params: ChainParams::default() };
+  assert!(new_app.genesis().is_ok());
+  let template = new_app.block_template("next".to_string()).unwrap();
+  let exported = template.to_json();
+  let mined = BlockTemplate::from_json(&exported).unwrap().mine();
+  let result = new_app.submit_mined(&serde_json::to_string(&mined).unwrap());
+  assert!(result.is_ok());
+  assert_eq!(new_app.blocks[1], mined);
+}
+
+#[test]
+fn rejects_malformed_mined_blocks() {
+  let mut new_app = Blockchain::<Block> { blocks: vec![], params: ChainParams::default() };
+  assert!(new_app.genesis().is_ok());
+  let result = new_app.submit_mined("not a block");
+  assert!(matches!(result, Err(MalformedJson(_))));
+}
+
+#[test]
+fn rejects_mined_blocks_over_the_decode_limits() {
+  use crate::decode::{ BLOCK_JSON_OVERHEAD, DEFAULT_MAX_DEPTH };
+  let params = ChainParams { difficulty: Difficulty::DEMO, max_block_size: 1024, ..Default::default() };
+  let mut new_app = Blockchain::<Block> { blocks: vec![], params };
+  assert!(new_app.genesis().is_ok());
+  let oversized = " ".repeat(1024 + BLOCK_JSON_OVERHEAD + 1);
+  assert_eq!(new_app.submit_mined(&oversized), Err(InputTooLarge { size: oversized.len(), limit: 1024 + BLOCK_JSON_OVERHEAD }));
+  assert_eq!(new_app.submit_mined(&"[".repeat(DEFAULT_MAX_DEPTH + 1)), Err(InputTooDeep { limit: DEFAULT_MAX_DEPTH }));
+}
+
+#[test]
+fn quick_check_rejects_short_hashes() {
+  let block = Block {
+    id: BlockId(1),
+    hash: "0000ff".into(),
+    previous_hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
+    timestamp: 1643223669,
+    data: String::from("next"),
+    nonce: 2836,
+  };
+  assert!(!quick_check(&block, &ChainParams::default()));
+}
+
+#[test]
+fn quick_check_accepts_a_mined_hash() {
+  let block = Block {
+    id: BlockId(1),
+    hash: "00005ea81511a2a24a25a2055d5fc581879b8cfbedc5ddfb6918caed4917138e".into(),
+    previous_hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
+    timestamp: 1643223669,
+    data: String::from("next"),
+    nonce: 24271,
+  };
+  assert!(quick_check(&block, &ChainParams::default()));
+  assert!(!quick_check(&block, &ChainParams { difficulty: Difficulty(20), ..Default::default() }));
+  use crate::params::Upgrade;
+  let upgraded = ChainParams::default().with_upgrade(Upgrade { difficulty: Some(Difficulty(20)), ..Upgrade::at(1) });
+  assert!(!quick_check(&block, &upgraded));
+}
+
+#[test]
+fn quick_check_passes_what_add_block_accepts() {
+  let params = ChainParams { difficulty: Difficulty::DEMO, ..Default::default() };
+  let mut new_app = Blockchain::<Block> { blocks: vec![], params: params.clone() };
+  assert!(new_app.genesis().is_ok());
+  let block = new_app.block_template("next".to_string()).unwrap().mine();
+  assert!(quick_check(&block, &params));
+  assert!(quick_check_json(&serde_json::to_string(&block).unwrap(), &params));
+  assert_eq!(new_app.add_block(block), Ok(()));
+}
+
+#[test]
+fn quick_check_json_drops_oversized_blocks_before_decoding() {
+  use crate::decode::BLOCK_JSON_OVERHEAD;
+  let params: ChainParams = ChainParams { difficulty: Difficulty(0), max_block_size: 64, ..Default::default() };
+  let block = Block::new(1, "genesis", "x".repeat(64 + BLOCK_JSON_OVERHEAD));
+  assert!(!quick_check_json(&serde_json::to_string(&block).unwrap(), &params));
+}
+
+#[test]
+fn reports_height_of_the_tip() {
+  let mut new_app = Blockchain::<Block> { blocks: vec![], params: ChainParams::default() };
+  assert!(new_app.genesis().is_ok());
+  let next_block = Block::new(new_app.blocks[0].id + 1, &new_app.blocks[0].hash, "next".to_string());
+  assert!(new_app.add_block(next_block.clone()).is_ok());
+  assert_eq!(new_app.height(), Some(BlockHeight(1)));
+  assert_eq!(new_app.block_at(BlockHeight(1)), Some(&next_block));
+}
+
+#[test]
+fn validates_against_its_own_difficulty() {
+  let mut new_app = Blockchain::with_params(ChainParams { difficulty: Difficulty::DEMO, ..Default::default() });
+  assert!(new_app.genesis().is_ok());
+  let template = new_app.block_template("next".to_string()).unwrap();
+  assert_eq!(template.difficulty, Difficulty::DEMO);
+  let easy_block = template.mine();
+  assert!(new_app.add_block(easy_block.clone()).is_ok());
+
+  let mut strict_app = Blockchain::new();
+  strict_app.blocks = new_app.blocks[..1].to_vec();
+  assert_eq!(strict_app.add_block(easy_block), Err(InvalidBlock(BlockValidationError::DifficultyNotMet)));
+}
+
+#[test]
+fn wont_choose_a_remote_chain_with_easier_blocks() {
+  let mut remote = Blockchain::with_params(ChainParams { difficulty: Difficulty(0), ..Default::default() });
+  let genesis_block = Block {
+    id: BlockId(0),
+    hash: "0000dbeb9e573d5382c63fd9a222c3720a4341b06416348fc5bbc0d19380a248".into(),
+    previous_hash: "genesis".into(),
+    timestamp: 1643223000,
+    data: "genesis!".to_string(),
+    nonce: 44475
+  };
+  remote.blocks.push(genesis_block);
+  let template = BlockTemplate {
+    id: BlockId(1),
+    previous_hash: "0000dbeb9e573d5382c63fd9a222c3720a4341b06416348fc5bbc0d19380a248".into(),
+    timestamp: 1643223669,
+    data: "next".to_string(),
+    difficulty: Difficulty(0)
+  };
+  let easy_block = template.mine();
+  assert_eq!(easy_block.nonce, 0);
+  assert!(!Difficulty::MEDIUM.is_met_by(&easy_block.hash));
+  assert!(remote.add_block(easy_block).is_ok());
+  assert!(remote.is_chain_valid());
+
+  let mut local = Blockchain::new();
+  local.choose_chain(&remote);
+  assert!(local.blocks.is_empty());
+}
+
+#[test]
+fn summarizes_a_valid_chain() {
+  let mut new_app = Blockchain::<Block> { blocks: vec![], params: ChainParams::default() };
+  let genesis_block = Block {
+    id: BlockId(0),
+    hash: "0000dbeb9e573d5382c63fd9a222c3720a4341b06416348fc5bbc0d19380a248".into(),
+    previous_hash: "genesis".into(),
+    timestamp: 1643223000,
+    data: "genesis!".to_string(),
+    nonce: 44475
+  };
+  new_app.blocks.push(genesis_block);
+  let block = Block {
+    id: BlockId(1),
+    hash: "0000cc07887fb749c99974e8e93debb64e205086f6d0962ef17bf6f0bb295f3e".into(),
+    previous_hash: "0000dbeb9e573d5382c63fd9a222c3720a4341b06416348fc5bbc0d19380a248".into(),
+    timestamp: 1643223669,
+    data: String::from("next"),
+    nonce: 236492,
+  };
+  assert!(new_app.add_block(block).is_ok());
+  assert_eq!(
+    new_app.summary_json(),
+    r#"{"height":1,"tip_hash":"0000cc07887fb749c99974e8e93debb64e205086f6d0962ef17bf6f0bb295f3e","work":131072,"difficulty":16,"last_timestamp":1643223669,"valid":true}"#
+  );
+}
+
+#[test]
+fn validates_blocks_under_the_params_at_their_height() {
+  use crate::params::Upgrade;
+  let params = ChainParams { difficulty: Difficulty::DEMO, ..Default::default() }
+    .with_upgrade(Upgrade { difficulty: Some(Difficulty::EASY), ..Upgrade::at(1) });
+  let mut new_app = Blockchain::<Block> { blocks: vec![], params };
+  let genesis_block = Block {
+    id: BlockId(0),
+    hash: "0000dbeb9e573d5382c63fd9a222c3720a4341b06416348fc5bbc0d19380a248".into(),
+    previous_hash: "genesis".into(),
+    timestamp: 1643223000,
+    data: "genesis!".to_string(),
+    nonce: 44475
+  };
+  new_app.blocks.push(genesis_block.clone());
+  let easy_enough_before = Block {
+    id: BlockId(1),
+    hash: format!("00ff{}", "0".repeat(60)).into(),
+    previous_hash: genesis_block.hash.clone(),
+    timestamp: 1643223669,
+    data: String::from("next"),
+    nonce: 0,
+  };
+  assert_eq!(new_app.validate_block(&easy_enough_before, &genesis_block), Err(BlockValidationError::DifficultyNotMet));
+  let before_upgrade = Blockchain::<Block> { blocks: vec![], params: ChainParams { difficulty: Difficulty::DEMO, ..Default::default() } };
+  assert_eq!(before_upgrade.validate_block(&easy_enough_before, &genesis_block), Err(BlockValidationError::HashMismatch));
+
+  let block = Block {
+    id: BlockId(1),
+    hash: "0000cc07887fb749c99974e8e93debb64e205086f6d0962ef17bf6f0bb295f3e".into(),
+    previous_hash: genesis_block.hash.clone(),
+    timestamp: 1643223669,
+    data: String::from("next"),
+    nonce: 236492,
+  };
+  assert!(new_app.add_block(block).is_ok());
+  assert_eq!(new_app.work(), 256 + 4096);
+  assert_eq!(new_app.summary().difficulty, Difficulty::EASY);
+}
+
+#[test]
+fn loads_chains_serialized_before_typed_hashes() {
+  let legacy = r#"[
+    {"id":0,"hash":"0000dbeb9e573d5382c63fd9a222c3720a4341b06416348fc5bbc0d19380a248","previous_hash":"genesis","timestamp":1643223000,"data":"genesis!","nonce":44475},
+    {"id":1,"hash":"0000cc07887fb749c99974e8e93debb64e205086f6d0962ef17bf6f0bb295f3e","previous_hash":"0000dbeb9e573d5382c63fd9a222c3720a4341b06416348fc5bbc0d19380a248","timestamp":1643223669,"data":"next","nonce":236492}
+  ]"#;
+  let blocks: Vec<Block> = serde_json::from_str(legacy).unwrap();
+  let chain = Blockchain::<Block> { blocks, params: ChainParams::default() };
+  assert!(chain.is_chain_valid());
+  assert_eq!(serde_json::to_string(&chain.blocks).unwrap(), legacy.split_whitespace().collect::<String>());
+}
+
+#[test]
+fn validates_chains_of_structured_data() {
+  use serde_json::{ json, Value };
+  let mut new_app = Blockchain::with_params(ChainParams::<Value> { difficulty: Difficulty::DEMO, ..Default::default() });
+  assert!(new_app.genesis_with(json!({ "supply": 100 })).is_ok());
+  let block = new_app.block_template(json!({ "to": "bob", "amount": 5 })).unwrap().mine();
+  let submitted = serde_json::to_string(&block).unwrap();
+  assert_eq!(new_app.submit_mined(&submitted), Ok(()));
+  assert!(new_app.is_chain_valid());
+
+  let forged = Block { data: json!({ "to": "bob", "amount": 500 }), ..block };
+  assert_eq!(new_app.validate_block(&forged, &new_app.blocks[0]), Err(BlockValidationError::HashMismatch));
+}
+
+#[cfg(feature = "debug-invariants")]
+#[test]
+#[should_panic(expected = "blockchain invariants broken:\nblock 1")]
+fn panics_when_invariants_break() {
+  let mut new_app = Blockchain::<Block> { blocks: vec![], params: ChainParams { difficulty: Difficulty::DEMO, ..Default::default() } };
+  assert!(new_app.genesis().is_ok());
+  new_app.blocks.push(Block::new(1, "not_the_previous_hash", "next".to_string()));
+  let tail = new_app.blocks[1].clone();
+  let _ = new_app.add_block(Block::new(tail.id + 1, &tail.hash, "after".to_string()));
+}
+
+#[cfg(feature = "debug-invariants")]
+#[test]
+#[should_panic(expected = "blockchain invariants broken:\ngenesis block 0")]
+fn checks_the_genesis_block_for_invariants() {
+  use crate::crypto::{ Keypair, Validators };
+  let validator = Keypair::from_seed([7; 32]);
+  let params = ChainParams { difficulty: Difficulty::DEMO, ..Default::default() }.with_rule(Validators::new([validator.public_key()]));
+  let difficulty = Difficulty::DEMO;
+  let outsider_genesis = Block::new_signed(&Keypair::from_seed([8; 32]), difficulty, 0, "genesis", "genesis!".to_string());
+  let mut new_app = Blockchain { blocks: vec![outsider_genesis.clone()], params };
+  let _ = new_app.add_block(Block::new_signed(&validator, difficulty, 1, &outsider_genesis.hash, "next".to_string()));
+}
+
+#[test]
+fn survives_the_last_possible_id() {
+  let params: ChainParams = ChainParams { difficulty: Difficulty(0), ..Default::default() };
+  let genesis = BlockTemplate { id: BlockId(u64::MAX), previous_hash: "genesis".into(), timestamp: Utc::now().timestamp(), data: "genesis!".to_string(), difficulty: Difficulty(0) }.mine();
+  let wrapped = BlockTemplate { id: BlockId(0), previous_hash: genesis.hash.clone(), timestamp: genesis.timestamp, data: "next".to_string(), difficulty: Difficulty(0) }.mine();
+  let mut new_app = Blockchain { blocks: vec![genesis, wrapped.clone()], params };
+  assert!(!new_app.is_chain_valid());
+  new_app.blocks.pop();
+  assert_eq!(new_app.block_template("next".to_string()), Err(InvalidBlock(BlockValidationError::IdOutOfSequence)));
+  assert_eq!(new_app.add_block(wrapped), Err(InvalidBlock(BlockValidationError::IdOutOfSequence)));
+}
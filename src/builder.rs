@@ -0,0 +1,262 @@
+use chrono::{ DateTime, Utc };
+use crate::block::Block;
+use crate::blockchain::Blockchain;
+use crate::difficulty::Difficulty;
+use crate::params::ChainParams;
+use crate::template::BlockTemplate;
+use crate::types::{ BlockHash, BlockHeight, BlockId };
+
+/// Builds blocks field by field, mainly for tests.
+///
+/// Finish with [`BlockBuilder::mine`] for a properly mined block,
+/// or [`BlockBuilder::raw`] to take the fields exactly as set, valid or not.
+///
+/// # Examples
+/// ```
+/// # use simple_blockchain::builder::BlockBuilder;
+/// let genesis = BlockBuilder::new().data("genesis!").mine();
+/// let next = BlockBuilder::new().after(&genesis).data("next").mine();
+/// assert_eq!(next.id, 1);
+/// assert_eq!(next.previous_hash, genesis.hash);
+///
+/// let forged = BlockBuilder::new().after(&genesis).hash("0000ff").raw();
+/// assert_eq!(forged.hash, "0000ff");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockBuilder {
+  id: BlockId,
+  hash: BlockHash,
+  previous_hash: BlockHash,
+  timestamp: i64,
+  data: String,
+  nonce: u64,
+}
+
+impl BlockBuilder {
+  /// Starts a genesis-like block: id 0, previous hash `"genesis"`, the current time and no data.
+  pub fn new() -> Self {
+    Self {
+      id: BlockId(0),
+      hash: BlockHash::default(),
+      previous_hash: "genesis".into(),
+      timestamp: Utc::now().timestamp(),
+      data: String::new(),
+      nonce: 0
+    }
+  }
+
+  /// Sets the id and previous hash to follow `previous_block`.
+  /// After a block with the last possible id, the id stays the same, so the block is out of sequence.
+  pub fn after(self, previous_block: &Block) -> Self {
+    Self { id: previous_block.id.checked_add(1).unwrap_or(previous_block.id), previous_hash: previous_block.hash.clone(), ..self }
+  }
+
+  /// Sets the id.
+  pub fn id(self, id: impl Into<BlockId>) -> Self {
+    Self { id: id.into(), ..self }
+  }
+
+  /// Sets the hash. Only used by [`BlockBuilder::raw`]; mining computes the hash.
+  pub fn hash(self, hash: impl Into<BlockHash>) -> Self {
+    Self { hash: hash.into(), ..self }
+  }
+
+  /// Sets the previous hash.
+  pub fn previous_hash(self, previous_hash: impl Into<BlockHash>) -> Self {
+    Self { previous_hash: previous_hash.into(), ..self }
+  }
+
+  /// Sets the timestamp, in seconds since the Unix epoch.
+  pub fn timestamp(self, timestamp: i64) -> Self {
+    Self { timestamp, ..self }
+  }
+
+  /// Sets the timestamp from a date and time, to the second.
+  pub fn datetime(self, datetime: DateTime<Utc>) -> Self {
+    Self { timestamp: datetime.timestamp(), ..self }
+  }
+
+  /// Sets the data.
+  pub fn data(self, data: impl Into<String>) -> Self {
+    Self { data: data.into(), ..self }
+  }
+
+  /// Sets the nonce. Only used by [`BlockBuilder::raw`]; mining computes the nonce.
+  pub fn nonce(self, nonce: u64) -> Self {
+    Self { nonce, ..self }
+  }
+
+  /// Returns the block's template, without a hash or nonce.
+  pub fn template(self) -> BlockTemplate {
+    BlockTemplate { id: self.id, previous_hash: self.previous_hash, timestamp: self.timestamp, data: self.data, difficulty: Difficulty::default() }
+  }
+
+  /// Mines the block at the default difficulty, ignoring any hash and nonce that were set.
+  pub fn mine(self) -> Block {
+    self.template().mine()
+  }
+
+  /// Returns the block with every field exactly as set.
+  pub fn raw(self) -> Block {
+    Block {
+      id: self.id,
+      hash: self.hash,
+      previous_hash: self.previous_hash,
+      timestamp: self.timestamp,
+      data: self.data,
+      nonce: self.nonce
+    }
+  }
+}
+
+impl Default for BlockBuilder {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+/// Builds fully mined blockchains, with optional forks, for tests and examples.
+///
+/// Every branch starts from the same genesis block. [`ChainBuilder::fork_at`] starts a new branch
+/// that shares the current branch's blocks up to a height, and later blocks go on the new branch.
+///
+/// # Examples
+/// ```
+/// # use simple_blockchain::builder::ChainBuilder;
+/// # use simple_blockchain::difficulty::Difficulty;
+/// # use simple_blockchain::params::ChainParams;
+/// let chains = ChainBuilder::new(ChainParams { difficulty: Difficulty::DEMO, ..Default::default() })
+///   .block("a")
+///   .block("b")
+///   .fork_at(1)
+///   .block("c'")
+///   .build_all();
+/// assert_eq!(chains[0].blocks.iter().map(|block| block.data.as_str()).collect::<Vec<_>>(), ["genesis!", "a", "b"]);
+/// assert_eq!(chains[1].blocks.iter().map(|block| block.data.as_str()).collect::<Vec<_>>(), ["genesis!", "a", "c'"]);
+/// assert!(chains.iter().all(|chain| chain.is_chain_valid()));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChainBuilder {
+  params: ChainParams,
+  branches: Vec<Vec<Block>>,
+}
+
+impl ChainBuilder {
+  /// Starts a chain with a genesis block mined at the difficulty in `params`.
+  pub fn new(params: ChainParams) -> Self {
+    let template = BlockBuilder::new().data("genesis!").template();
+    let genesis = BlockTemplate { difficulty: params.at_height(BlockHeight(0)).difficulty, ..template }.mine();
+    Self { params, branches: vec![vec![genesis]] }
+  }
+
+  /// Mines a block with `data` onto the end of the current branch.
+  pub fn block(self, data: impl Into<String>) -> Self {
+    self.block_at(data, Utc::now().timestamp())
+  }
+
+  /// Mines a block with `data` and `timestamp` onto the end of the current branch.
+  /// The timestamp isn't checked, so the block may break the timestamp rule.
+  pub fn block_at(mut self, data: impl Into<String>, timestamp: i64) -> Self {
+    let branch = self.branches.last_mut().unwrap();
+    let template = BlockBuilder::new().after(branch.last().unwrap()).timestamp(timestamp).data(data).template();
+    let difficulty = self.params.at_height(BlockHeight(template.id.0)).difficulty;
+    branch.push(BlockTemplate { difficulty, ..template }.mine());
+    self
+  }
+
+  /// Starts a new branch sharing the current branch's blocks up to and including `height`.
+  ///
+  /// # Panics
+  /// Panics if the current branch has no block at `height`.
+  pub fn fork_at(mut self, height: impl Into<BlockHeight>) -> Self {
+    let height = height.into().0;
+    let branch = self.branches.last().unwrap();
+    assert!(height < branch.len() as u64, "can't fork at height {} of a branch with {} blocks", height, branch.len());
+    let fork = branch[..=height as usize].to_vec();
+    self.branches.push(fork);
+    self
+  }
+
+  /// Returns the first branch as a blockchain.
+  pub fn build(self) -> Blockchain<Block> {
+    self.build_all().remove(0)
+  }
+
+  /// Returns every branch as a blockchain, in the order they were started.
+  pub fn build_all(self) -> Vec<Blockchain<Block>> {
+    let params = self.params;
+    self.branches.into_iter()
+      .map(|blocks| Blockchain { blocks, params: params.clone() })
+      .collect()
+  }
+}
+
+#[test]
+fn mines_the_same_block_as_a_template() {
+  let block = BlockBuilder::new()
+    .id(69)
+    .previous_hash("0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43")
+    .timestamp(1643220097)
+    .data("foo")
+    .mine();
+  assert_eq!(block.nonce, 9386);
+  assert_eq!(block.hash, "00007751f1b92a8ac1bdc88407e7a85b4c0dd59313d8fa78ae2208dbcaaad604");
+}
+
+#[test]
+fn raw_keeps_every_field() {
+  let block = BlockBuilder::new()
+    .id(1)
+    .hash("0000ff")
+    .previous_hash("not_the_previous_hash")
+    .timestamp(1643223669)
+    .data("next")
+    .nonce(2836)
+    .raw();
+  let expected = Block {
+    id: BlockId(1),
+    hash: "0000ff".into(),
+    previous_hash: "not_the_previous_hash".into(),
+    timestamp: 1643223669,
+    data: String::from("next"),
+    nonce: 2836,
+  };
+  assert_eq!(block, expected);
+}
+
+#[test]
+fn builds_a_valid_chain() {
+  use crate::difficulty::Difficulty;
+  let chain = ChainBuilder::new(ChainParams { difficulty: Difficulty::DEMO, ..Default::default() })
+    .block("a")
+    .block("b")
+    .build();
+  assert_eq!(chain.blocks.len(), 3);
+  assert!(chain.is_chain_valid());
+}
+
+#[test]
+fn forks_share_a_prefix() {
+  use crate::difficulty::Difficulty;
+  let chains = ChainBuilder::new(ChainParams { difficulty: Difficulty::DEMO, ..Default::default() })
+    .block("a")
+    .block("b")
+    .fork_at(1)
+    .block("c'")
+    .block("d'")
+    .build_all();
+  assert_eq!(chains[0].blocks[..2], chains[1].blocks[..2]);
+  assert_ne!(chains[0].blocks[2], chains[1].blocks[2]);
+  assert_eq!(chains[1].blocks.len(), 4);
+
+  let mut local = chains[0].clone();
+  local.choose_chain(&chains[1]);
+  assert_eq!(local.blocks, chains[1].blocks);
+}
+
+#[test]
+#[should_panic(expected = "can't fork at height 3")]
+fn cant_fork_past_the_tip() {
+  use crate::difficulty::Difficulty;
+  ChainBuilder::new(ChainParams { difficulty: Difficulty::DEMO, ..Default::default() }).block("a").fork_at(3);
+}
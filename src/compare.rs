@@ -0,0 +1,131 @@
+use crate::block::{ Block, BlockData };
+use crate::blockchain::Blockchain;
+use crate::error::BlockValidationError;
+use crate::params::ChainParams;
+use crate::types::{ BlockHash, BlockHeight };
+
+/// Where two chains stop holding the same blocks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence<T = String> {
+  /// The height of the first block that differs.
+  pub height: BlockHeight,
+  /// This chain's block at that height, if it has one.
+  pub local: Option<Block<T>>,
+  /// The other chain's block at that height, if it has one.
+  pub remote: Option<Block<T>>,
+  /// The rules whose outcome for either block depends on whose params it's checked under.
+  pub rule_differences: Vec<RuleDifference>,
+}
+
+/// A rule that judges a block differently under the two chains' params.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleDifference {
+  pub rule: String,
+  /// The block the rule was checked against.
+  pub block_hash: BlockHash,
+  /// The outcome under this chain's params, or `None` if they don't have the rule.
+  pub under_local: Option<Result<(), BlockValidationError>>,
+  /// The outcome under the other chain's params, or `None` if they don't have the rule.
+  pub under_remote: Option<Result<(), BlockValidationError>>,
+}
+
+impl<T: BlockData> Blockchain<Block<T>> {
+  /// Finds the first block where this chain and `other` differ,
+  /// and explains which rules judge the differing blocks differently under the two chains' params.
+  /// Returns `None` if both chains hold the same blocks.
+  ///
+  /// Every rule is run, not just up to the first failure, so all differences show up at once.
+  /// Genesis blocks have no previous block to check against, so a differing genesis lists no rule differences.
+  ///
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::builder::ChainBuilder;
+  /// # use simple_blockchain::difficulty::Difficulty;
+  /// # use simple_blockchain::error::BlockValidationError;
+  /// # use simple_blockchain::params::ChainParams;
+  /// let params = ChainParams { difficulty: Difficulty::DEMO, ..Default::default() };
+  /// let chains = ChainBuilder::new(params.clone())
+  ///   .block("shared")
+  ///   .block("local")
+  ///   .fork_at(1)
+  ///   .block("remote")
+  ///   .build_all();
+  /// let local = &chains[0];
+  /// let mut remote = chains[1].clone();
+  /// remote.params = ChainParams { max_block_size: 64, ..params };
+  ///
+  /// let divergence = local.compare_and_explain(&remote).unwrap();
+  /// assert_eq!(divergence.height, 2);
+  /// assert_eq!(divergence.remote.unwrap().data, "remote");
+  /// let difference = &divergence.rule_differences[0];
+  /// assert_eq!(difference.rule, "size");
+  /// assert_eq!(difference.under_local, Some(Ok(())));
+  /// assert_eq!(difference.under_remote, Some(Err(BlockValidationError::TooLarge)));
+  /// ```
+  pub fn compare_and_explain(&self, other: &Blockchain<Block<T>>) -> Option<Divergence<T>> {
+    let length = self.blocks.len().max(other.blocks.len());
+    let index = (0..length).find(|&index| self.blocks.get(index) != other.blocks.get(index))?;
+    let local = self.blocks.get(index).cloned();
+    let remote = other.blocks.get(index).cloned();
+
+    let mut rule_differences = Vec::new();
+    if index > 0 {
+      let previous_block = &self.blocks[index - 1];
+      for block in local.iter().chain(remote.iter()) {
+        rule_differences.extend(rule_differences_for(block, previous_block, &self.params, &other.params));
+      }
+    }
+    Some(Divergence { height: BlockHeight(index as u64), local, remote, rule_differences })
+  }
+}
+
+fn rule_differences_for<T: BlockData>(block: &Block<T>, previous_block: &Block<T>, local: &ChainParams<T>, remote: &ChainParams<T>) -> Vec<RuleDifference> {
+  let local = &local.at_height(BlockHeight(block.id.0));
+  let remote = &remote.at_height(BlockHeight(block.id.0));
+  let outcome = |params: &ChainParams<T>, name: &str| params.rules.iter()
+    .find(|rule| rule.name() == name)
+    .map(|rule| rule.check(block, previous_block, params));
+  let remote_only = remote.rules.iter().filter(|rule| !local.rules.iter().any(|local_rule| local_rule.name() == rule.name()));
+
+  local.rules.iter().chain(remote_only)
+    .map(|rule| RuleDifference {
+      rule: rule.name().to_string(),
+      block_hash: block.hash.clone(),
+      under_local: outcome(local, rule.name()),
+      under_remote: outcome(remote, rule.name()),
+    })
+    .filter(|difference| difference.under_local != difference.under_remote)
+    .collect()
+}
+
+#[test]
+fn identical_chains_do_not_diverge() {
+  use crate::builder::ChainBuilder;
+  use crate::difficulty::Difficulty;
+  let chain = ChainBuilder::new(ChainParams { difficulty: Difficulty::DEMO, ..Default::default() }).block("next").build();
+  let mut other = chain.clone();
+  other.params.max_block_size = 1;
+  assert_eq!(chain.compare_and_explain(&other), None);
+}
+
+#[test]
+fn explains_rules_only_one_side_runs() {
+  use crate::builder::ChainBuilder;
+  use crate::difficulty::Difficulty;
+  let params = ChainParams { difficulty: Difficulty::DEMO, ..Default::default() };
+  let local = ChainBuilder::new(params.clone()).build();
+  let mut remote = local.clone();
+  remote.params = params.without_rule("linkage");
+  let unlinked = crate::builder::BlockBuilder::new().after(&local.blocks[0]).previous_hash("elsewhere").mine();
+  remote.blocks.push(unlinked.clone());
+
+  let divergence = local.compare_and_explain(&remote).unwrap();
+  assert_eq!(divergence.height, 1);
+  assert_eq!(divergence.local, None);
+  assert_eq!(divergence.rule_differences, [RuleDifference {
+    rule: "linkage".to_string(),
+    block_hash: unlinked.hash,
+    under_local: Some(Err(BlockValidationError::PreviousHashMismatch)),
+    under_remote: None,
+  }]);
+}
@@ -0,0 +1,108 @@
+//! Import and export in the JSON shape of the tutorial this crate follows.
+//!
+//! The tutorial's Rust code writes blocks with the same fields, order and lowercase hex hashes as [`Block`],
+//! and hashes the same preimage, so its chains validate here unchanged once parsed.
+//! Ports that write camelCase keys (`previousHash`) or uppercase hex are accepted too.
+use serde::Deserialize;
+use crate::block::Block;
+use crate::decode::{ self, DecodeLimits };
+use crate::error::BlockchainError;
+use crate::timestamp;
+
+#[derive(Deserialize)]
+struct TutorialBlock {
+  id: u64,
+  hash: String,
+  #[serde(alias = "previousHash")]
+  previous_hash: String,
+  #[serde(deserialize_with = "timestamp::deserialize")]
+  timestamp: i64,
+  data: String,
+  nonce: u64,
+}
+
+impl From<TutorialBlock> for Block {
+  fn from(block: TutorialBlock) -> Self {
+    Block {
+      id: block.id.into(),
+      hash: normalize_hex(block.hash).into(),
+      previous_hash: normalize_hex(block.previous_hash).into(),
+      timestamp: block.timestamp,
+      data: block.data,
+      nonce: block.nonce,
+    }
+  }
+}
+
+/// Writes a block exactly as the tutorial does: `id`, `hash`, `previous_hash`, `timestamp`, `data`, `nonce`,
+/// with lowercase hex hashes and the timestamp in Unix seconds.
+///
+/// # Examples
+/// ```
+/// # use simple_blockchain::block::Block;
+/// # use simple_blockchain::compat::to_tutorial_json;
+/// let block = Block { id: 1.into(), hash: "00ab".into(), previous_hash: "genesis".into(), timestamp: 1643223669, data: "next".to_string(), nonce: 7 };
+/// assert_eq!(
+///   to_tutorial_json(&block),
+///   r#"{"id":1,"hash":"00ab","previous_hash":"genesis","timestamp":1643223669,"data":"next","nonce":7}"#
+/// );
+/// ```
+pub fn to_tutorial_json(block: &Block) -> String {
+  serde_json::to_string(block).unwrap()
+}
+
+/// Writes a chain of blocks as the tutorial's JSON array.
+pub fn chain_to_tutorial_json(blocks: &[Block]) -> String {
+  serde_json::to_string(blocks).unwrap()
+}
+
+/// Reads a block written by the tutorial's code or a port of it.
+///
+/// # Examples
+/// ```
+/// # use simple_blockchain::compat::from_tutorial_json;
+/// let block = from_tutorial_json(r#"{"id":1,"hash":"00AB","previousHash":"genesis","timestamp":1643223669,"data":"next","nonce":7}"#).unwrap();
+/// assert_eq!(block.hash, "00ab");
+/// assert_eq!(block.previous_hash, "genesis");
+/// ```
+///
+/// # Errors
+/// Returns [`BlockchainError::InputTooLarge`] or [`BlockchainError::InputTooDeep`] if the input is over the default [`DecodeLimits`],
+/// and [`BlockchainError::MalformedJson`] if it isn't a block in any accepted shape.
+pub fn from_tutorial_json(json: &str) -> Result<Block, BlockchainError> {
+  decode::from_json::<TutorialBlock>(json, DecodeLimits::default()).map(Block::from)
+}
+
+/// Reads a chain written by the tutorial's code or a port of it, as a JSON array of blocks.
+///
+/// # Errors
+/// Returns [`BlockchainError::InputTooLarge`] or [`BlockchainError::InputTooDeep`] if the input is over [`DecodeLimits::for_chain`],
+/// and [`BlockchainError::MalformedJson`] if it isn't an array of blocks in any accepted shape.
+pub fn chain_from_tutorial_json(json: &str) -> Result<Vec<Block>, BlockchainError> {
+  decode::from_json::<Vec<TutorialBlock>>(json, DecodeLimits::for_chain())
+    .map(|blocks| blocks.into_iter().map(Block::from).collect())
+}
+
+// Hashes are compared as lowercase hex; anything else, like the "genesis" placeholder, is kept as is.
+fn normalize_hex(hash: String) -> String {
+  if !hash.is_empty() && hash.chars().all(|c| c.is_ascii_hexdigit()) { hash.to_ascii_lowercase() } else { hash }
+}
+
+#[test]
+fn imports_a_tutorial_chain_that_validates() {
+  use crate::blockchain::Blockchain;
+  use crate::params::ChainParams;
+  let exported = r#"[
+    {"id":0,"hash":"0000DBEB9E573D5382C63FD9A222C3720A4341B06416348FC5BBC0D19380A248","previousHash":"genesis","timestamp":1643223000,"data":"genesis!","nonce":44475},
+    {"id":1,"hash":"0000CC07887FB749C99974E8E93DEBB64E205086F6D0962EF17BF6F0BB295F3E","previousHash":"0000DBEB9E573D5382C63FD9A222C3720A4341B06416348FC5BBC0D19380A248","timestamp":1643223669,"data":"next","nonce":236492}
+  ]"#;
+  let blocks = chain_from_tutorial_json(exported).unwrap();
+  let chain = Blockchain::<Block> { blocks, params: ChainParams::default() };
+  assert!(chain.is_chain_valid());
+  assert_eq!(chain_from_tutorial_json(&chain_to_tutorial_json(&chain.blocks)), Ok(chain.blocks));
+}
+
+#[test]
+fn rejects_blocks_missing_fields() {
+  assert!(matches!(from_tutorial_json(r#"{"id":1,"hash":"00ab"}"#), Err(BlockchainError::MalformedJson(_))));
+}
@@ -0,0 +1,265 @@
+//! Ed25519 keys, and blocks signed by the validator that made them.
+//!
+//! A signed block carries a [`Signed`] payload: its data plus the signer's public key and a signature over
+//! [`signing_message`]. The signature is part of the data, so the block's hash commits to it too.
+//! Blockchains only check signatures if their params include a [`Validators`] rule, which checks the genesis block too.
+use std::fmt;
+use chrono::Utc;
+use ed25519_dalek::{ Signature, Signer, SigningKey, Verifier, VerifyingKey };
+use serde::{ Serialize, Deserialize };
+use serde_json::Value;
+use crate::block::{ Block, BlockData };
+use crate::blockchain::Blockchain;
+use crate::error::{ BlockchainError, BlockchainError::*, BlockValidationError, BlockValidationError::* };
+use crate::difficulty::Difficulty;
+use crate::params::ChainParams;
+use crate::rules::Rule;
+use crate::template::BlockTemplate;
+use crate::types::{ BlockHeight, BlockId };
+
+/// An Ed25519 signing key and its public key.
+pub struct Keypair {
+  signing_key: SigningKey,
+}
+
+impl Keypair {
+  /// Generates a keypair from the operating system's random number generator.
+  ///
+  /// # Panics
+  /// Panics if the operating system can't provide randomness.
+  pub fn generate() -> Self {
+    let mut seed = [0; 32];
+    getrandom::getrandom(&mut seed).expect("the OS random number generator failed");
+    Self::from_seed(seed)
+  }
+
+  /// Derives the keypair for a 32-byte secret seed, e.g. one loaded from a key file.
+  pub fn from_seed(seed: [u8; 32]) -> Self {
+    Self { signing_key: SigningKey::from_bytes(&seed) }
+  }
+
+  /// Returns the 32-byte secret seed, to be stored somewhere safe.
+  pub fn seed(&self) -> [u8; 32] {
+    self.signing_key.to_bytes()
+  }
+
+  /// Returns the public key as lowercase hex.
+  pub fn public_key(&self) -> String {
+    hex::encode(self.signing_key.verifying_key().as_bytes())
+  }
+
+  /// Signs `message`, returning the signature as lowercase hex.
+  pub fn sign(&self, message: &[u8]) -> String {
+    hex::encode(self.signing_key.sign(message).to_bytes())
+  }
+}
+
+// Shows only the public key, so the secret doesn't end up in logs.
+impl fmt::Debug for Keypair {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    f.debug_struct("Keypair").field("public_key", &self.public_key()).finish_non_exhaustive()
+  }
+}
+
+/// Returns `true` if `signature` is `public_key`'s signature of `message`, both as hex.
+///
+/// # Examples
+/// ```
+/// # use simple_blockchain::crypto::{ Keypair, verify };
+/// let keypair = Keypair::generate();
+/// let signature = keypair.sign(b"next");
+/// assert!(verify(&keypair.public_key(), b"next", &signature));
+/// assert!(!verify(&keypair.public_key(), b"forged", &signature));
+/// ```
+pub fn verify(public_key: &str, message: &[u8], signature: &str) -> bool {
+  let Some(public_key) = hex::decode(public_key).ok().and_then(|bytes| <[u8; 32]>::try_from(bytes).ok()) else { return false };
+  let Some(signature) = hex::decode(signature).ok().and_then(|bytes| <[u8; 64]>::try_from(bytes).ok()) else { return false };
+  let Ok(public_key) = VerifyingKey::from_bytes(&public_key) else { return false };
+  public_key.verify(message, &Signature::from_bytes(&signature)).is_ok()
+}
+
+/// Block data signed by a validator.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Signed<T> {
+  pub data: T,
+  /// The signer's public key, as hex.
+  pub signer: String,
+  /// The signature over [`signing_message`], as hex.
+  pub signature: String,
+}
+
+impl<T: BlockData> BlockData for Signed<T> {}
+
+/// Returns the bytes a validator signs for a block: compact JSON of everything the block's hash covers
+/// except the nonce, so the block can be signed before it's mined.
+///
+/// # Examples
+/// ```
+/// # use simple_blockchain::crypto::signing_message;
+/// assert_eq!(
+///   signing_message(1.into(), "genesis", 1643223669, &"next".into()),
+///   br#"{"data":"next","id":1,"previous_hash":"genesis","timestamp":1643223669}"#
+/// );
+/// ```
+pub fn signing_message(id: BlockId, previous_hash: &str, timestamp: i64, data: &Value) -> Vec<u8> {
+  let content = serde_json::json!({
+    "id": id,
+    "timestamp": timestamp,
+    "previous_hash": previous_hash,
+    "data": data
+  });
+  content.to_string().into_bytes()
+}
+
+impl<T: BlockData> Block<Signed<T>> {
+  /// Creates a new block like [`Block::new`], with `data` signed by `keypair`, mined at `difficulty`.
+  ///
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::block::Block;
+  /// # use simple_blockchain::crypto::Keypair;
+  /// # use simple_blockchain::difficulty::Difficulty;
+  /// let keypair = Keypair::generate();
+  /// let block = Block::new_signed(&keypair, Difficulty::DEMO, 1, "genesis", "next".to_string());
+  /// assert_eq!(block.data.data, "next");
+  /// assert_eq!(block.data.signer, keypair.public_key());
+  /// assert!(block.has_valid_signature());
+  /// ```
+  pub fn new_signed(keypair: &Keypair, difficulty: Difficulty, id: impl Into<BlockId>, previous_hash: &str, data: T) -> Self {
+    signed_template(keypair, id.into(), previous_hash, data, difficulty).mine()
+  }
+
+  /// Returns `true` if the block's signature is its signer's, over this block's contents.
+  pub fn has_valid_signature(&self) -> bool {
+    let message = signing_message(self.id, &self.previous_hash, self.timestamp, &self.data.data.canonical());
+    verify(&self.data.signer, &message, &self.data.signature)
+  }
+}
+
+fn signed_template<T: BlockData>(keypair: &Keypair, id: BlockId, previous_hash: &str, data: T, difficulty: Difficulty) -> BlockTemplate<Signed<T>> {
+  let timestamp = Utc::now().timestamp();
+  let signature = keypair.sign(&signing_message(id, previous_hash, timestamp, &data.canonical()));
+  let data = Signed { data, signer: keypair.public_key(), signature };
+  BlockTemplate { id, previous_hash: previous_hash.into(), timestamp, data, difficulty }
+}
+
+impl<T: BlockData> Blockchain<Block<Signed<T>>> {
+  /// Initializes the blockchain with a genesis block carrying `data`, signed by `keypair`
+  /// and mined at the chain's difficulty.
+  ///
+  /// # Errors
+  /// Returns [`BlockchainError::InvalidChainLength`] if the blockchain is not empty,
+  /// and [`BlockchainError::InvalidBlock`] if the genesis block fails [`Blockchain::validate_genesis`],
+  /// e.g. because `keypair` isn't one of the chain's [`Validators`].
+  pub fn genesis_signed(&mut self, keypair: &Keypair, data: T) -> Result<(), BlockchainError> {
+    if !self.blocks.is_empty() { return Err(InvalidChainLength) };
+    let template = signed_template(keypair, BlockId(0), "genesis", data, self.params.at_height(BlockHeight(0)).difficulty);
+    let genesis_block = template.mine();
+    self.validate_genesis(&genesis_block).map_err(InvalidBlock)?;
+    self.blocks.push(genesis_block);
+    self.debug_check_invariants();
+    Ok(())
+  }
+}
+
+/// Blocks must be signed by one of these validators' public keys, as hex.
+/// The genesis block is checked too, with [`Rule::check_genesis`].
+/// Not a default rule, so params holding it can be serialized but not deserialized; see [`ChainParams`].
+///
+/// # Examples
+/// ```
+/// # use simple_blockchain::block::Block;
+/// # use simple_blockchain::blockchain::Blockchain;
+/// # use simple_blockchain::crypto::{ Keypair, Validators };
+/// # use simple_blockchain::difficulty::Difficulty;
+/// # use simple_blockchain::error::{ BlockchainError, BlockValidationError };
+/// # use simple_blockchain::params::ChainParams;
+/// let validator = Keypair::generate();
+/// let params = ChainParams { difficulty: Difficulty::DEMO, ..Default::default() }
+///   .with_rule(Validators::new([validator.public_key()]));
+/// let mut my_blockchain = Blockchain::with_params(params);
+/// my_blockchain.genesis_signed(&validator, "genesis!".to_string()).unwrap();
+///
+/// let difficulty = Difficulty::DEMO;
+/// let tip = my_blockchain.blocks[0].clone();
+/// let outsider = Block::new_signed(&Keypair::generate(), difficulty, 1, &tip.hash, "next".to_string());
+/// assert_eq!(my_blockchain.add_block(outsider), Err(BlockchainError::InvalidBlock(BlockValidationError::InvalidSignature)));
+/// let signed = Block::new_signed(&validator, difficulty, 1, &tip.hash, "next".to_string());
+/// assert_eq!(my_blockchain.add_block(signed), Ok(()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Validators {
+  pub public_keys: Vec<String>,
+}
+
+impl Validators {
+  pub fn new(public_keys: impl IntoIterator<Item = String>) -> Self {
+    Self { public_keys: public_keys.into_iter().collect() }
+  }
+}
+
+impl<T: BlockData> Rule<Signed<T>> for Validators {
+  fn name(&self) -> &str {
+    "validator-signature"
+  }
+
+  fn check(&self, block: &Block<Signed<T>>, _: &Block<Signed<T>>, _: &ChainParams<Signed<T>>) -> Result<(), BlockValidationError> {
+    if !self.public_keys.contains(&block.data.signer) || !block.has_valid_signature() { return Err(InvalidSignature) };
+    Ok(())
+  }
+
+  fn check_genesis(&self, genesis: &Block<Signed<T>>, params: &ChainParams<Signed<T>>) -> Result<(), BlockValidationError> {
+    self.check(genesis, genesis, params)
+  }
+}
+
+#[test]
+fn signatures_cover_the_block_contents() {
+  use crate::difficulty::Difficulty;
+  let keypair = Keypair::from_seed([7; 32]);
+  let block = Block::new_signed(&keypair, Difficulty::DEMO, 1, "genesis", "next".to_string());
+  assert!(block.has_valid_signature());
+
+  let tampered = [
+    Block { id: BlockId(2), ..block.clone() },
+    Block { previous_hash: "elsewhere".into(), ..block.clone() },
+    Block { timestamp: block.timestamp + 1, ..block.clone() },
+    Block { data: Signed { data: "forged".to_string(), ..block.data.clone() }, ..block.clone() },
+    Block { data: Signed { signer: Keypair::from_seed([8; 32]).public_key(), ..block.data.clone() }, ..block.clone() },
+    Block { data: Signed { signature: "00".repeat(64), ..block.data.clone() }, ..block.clone() },
+  ];
+  assert!(tampered.iter().all(|block| !block.has_valid_signature()));
+  assert!(Block { nonce: block.nonce + 1, ..block }.has_valid_signature());
+}
+
+#[test]
+fn restores_keypairs_from_their_seed() {
+  let keypair = Keypair::generate();
+  let restored = Keypair::from_seed(keypair.seed());
+  assert_eq!(restored.public_key(), keypair.public_key());
+  assert!(verify(&keypair.public_key(), b"next", &restored.sign(b"next")));
+  assert!(!format!("{:?}", keypair).contains(&hex::encode(keypair.seed())));
+  assert!(!verify("not hex", b"next", &keypair.sign(b"next")));
+}
+
+#[test]
+fn validators_check_the_genesis_signer() {
+  use crate::difficulty::Difficulty;
+  let validator = Keypair::from_seed([7; 32]);
+  let params = ChainParams { difficulty: Difficulty::DEMO, ..Default::default() }
+    .with_rule(Validators::new([validator.public_key()]));
+  let mut outsiders = Blockchain::with_params(params.clone());
+  assert_eq!(outsiders.genesis_signed(&Keypair::from_seed([8; 32]), "genesis!".to_string()), Err(InvalidBlock(InvalidSignature)));
+  assert!(outsiders.blocks.is_empty());
+
+  let difficulty = Difficulty::DEMO;
+  let mut chain = Blockchain::with_params(params);
+  assert!(chain.genesis_signed(&validator, "genesis!".to_string()).is_ok());
+  let tip = chain.blocks[0].clone();
+  assert!(chain.add_block(Block::new_signed(&validator, difficulty, 1, &tip.hash, "next".to_string())).is_ok());
+  assert!(chain.is_chain_valid());
+
+  chain.blocks[0] = Block::new_signed(&Keypair::from_seed([8; 32]), difficulty, 0, "genesis", "genesis!".to_string());
+  chain.blocks[1] = Block::new_signed(&validator, difficulty, 1, &chain.blocks[0].hash.clone(), "next".to_string());
+  assert!(!chain.is_chain_valid());
+}
@@ -0,0 +1,127 @@
+//! JSON decoding with size and nesting caps, for input that may come from untrusted peers.
+//!
+//! Every JSON entry point in the crate decodes through [`from_json`].
+//! The size cap also bounds allocation: decoding never allocates much more than the input's size.
+use serde::de::DeserializeOwned;
+use crate::error::{ BlockchainError, BlockchainError::* };
+use crate::params::DEFAULT_MAX_BLOCK_SIZE;
+
+/// Room for a block's JSON beyond its [`Block::size`](crate::block::Block::size):
+/// the hash, which isn't in the preimage, and an RFC 3339 timestamp.
+pub const BLOCK_JSON_OVERHEAD: usize = 256;
+
+/// The default [`DecodeLimits::max_depth`]. A block is two levels deep; the rest is room for structured data.
+pub const DEFAULT_MAX_DEPTH: usize = 32;
+
+/// The default [`DecodeLimits::max_bytes`] for a whole chain, 256 MiB.
+pub const DEFAULT_MAX_CHAIN_BYTES: usize = 256 * 1024 * 1024;
+
+/// The most input [`from_json`] accepts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecodeLimits {
+  /// The longest input, in bytes.
+  pub max_bytes: usize,
+  /// The deepest nesting of arrays and objects.
+  pub max_depth: usize,
+}
+
+impl DecodeLimits {
+  /// Limits for one block or template whose size may be up to `max_block_size`.
+  pub fn for_block(max_block_size: usize) -> Self {
+    Self { max_bytes: max_block_size.saturating_add(BLOCK_JSON_OVERHEAD), max_depth: DEFAULT_MAX_DEPTH }
+  }
+
+  /// Limits for a JSON array of blocks.
+  /// The array adds a level of nesting, so the depth allowed inside each block stays the same.
+  pub fn for_chain() -> Self {
+    Self { max_bytes: DEFAULT_MAX_CHAIN_BYTES, max_depth: DEFAULT_MAX_DEPTH + 1 }
+  }
+}
+
+/// Limits for one block of up to [`DEFAULT_MAX_BLOCK_SIZE`].
+impl Default for DecodeLimits {
+  fn default() -> Self {
+    Self::for_block(DEFAULT_MAX_BLOCK_SIZE)
+  }
+}
+
+/// Decodes JSON, rejecting it before parsing if it's longer or more deeply nested than `limits` allow.
+///
+/// # Examples
+/// ```
+/// # use simple_blockchain::decode::{ from_json, DecodeLimits };
+/// let limits = DecodeLimits { max_bytes: 16, max_depth: 2 };
+/// assert_eq!(from_json::<Vec<Vec<u8>>>("[[1],[2]]", limits), Ok(vec![vec![1], vec![2]]));
+/// ```
+///
+/// # Errors
+/// Returns [`BlockchainError::InputTooLarge`] or [`BlockchainError::InputTooDeep`] if the input is over a limit,
+/// and [`BlockchainError::MalformedJson`] if it doesn't parse.
+/// ```
+/// # use simple_blockchain::decode::{ from_json, DecodeLimits };
+/// # use simple_blockchain::error::BlockchainError;
+/// let limits = DecodeLimits { max_bytes: 16, max_depth: 2 };
+/// assert_eq!(from_json::<Vec<u8>>("[1,2,3,4,5,6,7,8,9]", limits), Err(BlockchainError::InputTooLarge { size: 19, limit: 16 }));
+/// assert_eq!(from_json::<Vec<u8>>("[[[1]]]", limits), Err(BlockchainError::InputTooDeep { limit: 2 }));
+/// ```
+pub fn from_json<T: DeserializeOwned>(json: impl AsRef<[u8]>, limits: DecodeLimits) -> Result<T, BlockchainError> {
+  let json = json.as_ref();
+  if json.len() > limits.max_bytes { return Err(InputTooLarge { size: json.len(), limit: limits.max_bytes }) };
+  if exceeds_depth(json, limits.max_depth) { return Err(InputTooDeep { limit: limits.max_depth }) };
+  serde_json::from_slice(json).map_err(|error| MalformedJson(error.to_string()))
+}
+
+// Counts brackets outside strings. Doesn't validate anything else; that's left to the parser.
+fn exceeds_depth(json: &[u8], max_depth: usize) -> bool {
+  let mut depth = 0usize;
+  let mut in_string = false;
+  let mut escaped = false;
+  for &byte in json {
+    if in_string {
+      match byte {
+        _ if escaped => escaped = false,
+        b'\\' => escaped = true,
+        b'"' => in_string = false,
+        _ => {}
+      }
+      continue;
+    }
+    match byte {
+      b'"' => in_string = true,
+      b'[' | b'{' => {
+        depth += 1;
+        if depth > max_depth { return true };
+      },
+      b']' | b'}' => depth = depth.saturating_sub(1),
+      _ => {}
+    }
+  }
+  false
+}
+
+#[test]
+fn ignores_brackets_inside_strings() {
+  let limits = DecodeLimits { max_bytes: 64, max_depth: 1 };
+  assert_eq!(from_json::<Vec<String>>(r#"["[[[{{{", "\"[[["]"#, limits), Ok(vec!["[[[{{{".to_string(), "\"[[[".to_string()]));
+}
+
+#[test]
+fn rejects_deep_nesting_without_overflowing_the_stack() {
+  let json = "[".repeat(1_000_000);
+  let limits = DecodeLimits { max_bytes: usize::MAX, max_depth: DEFAULT_MAX_DEPTH };
+  assert_eq!(from_json::<serde_json::Value>(&json, limits), Err(InputTooDeep { limit: DEFAULT_MAX_DEPTH }));
+}
+
+#[test]
+fn survives_arbitrary_bytes() {
+  // A fixed xorshift stream, so failures reproduce.
+  let mut state = 0x2545f4914f6cdd1du64;
+  let mut next = move || { state ^= state << 13; state ^= state >> 7; state ^= state << 17; state };
+  let alphabet = br#"{}[]":,\0123456789.eE+-truefalsnl "#;
+  for _ in 0..2000 {
+    let length = (next() % 64) as usize;
+    let input = (0..length).map(|_| alphabet[(next() % alphabet.len() as u64) as usize]).collect::<Vec<_>>();
+    let result = from_json::<crate::block::Block>(&input, DecodeLimits::default());
+    assert!(matches!(result, Err(MalformedJson(_)) | Err(InputTooDeep { .. })), "{:?}", String::from_utf8_lossy(&input));
+  }
+}
@@ -0,0 +1,197 @@
+use std::time::Duration;
+use serde::{ Serialize, Deserialize };
+use crate::helpers::leading_zero_bits;
+
+/// The number of leading zero bits a block hash needs.
+///
+/// Each extra bit doubles the expected number of hashes needed to mine a block.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Difficulty(pub u32);
+
+impl Difficulty {
+  /// Mines in a blink. Good for tests and live demos.
+  pub const DEMO: Difficulty = Difficulty(8);
+  /// Mines in a few milliseconds.
+  pub const EASY: Difficulty = Difficulty(12);
+  /// The difficulty the chain uses, about 65 thousand hashes per block.
+  pub const MEDIUM: Difficulty = Difficulty(16);
+  /// Every bit of a SHA-256 hash. Nothing harder can be met.
+  pub const MAX: Difficulty = Difficulty(256);
+
+  /// Picks the difficulty that takes `seconds` on average to mine at `hashrate_estimate` hashes per second.
+  ///
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::difficulty::Difficulty;
+  /// assert_eq!(Difficulty::from_target_block_time(65536.0, 1.0), Difficulty(16));
+  /// assert_eq!(Difficulty::from_target_block_time(1000.0, 10.0), Difficulty(13));
+  /// assert_eq!(Difficulty::from_target_block_time(1000.0, 0.0), Difficulty(0));
+  /// ```
+  pub fn from_target_block_time(hashrate_estimate: f64, seconds: f64) -> Self {
+    let hashes = hashrate_estimate * seconds;
+    if hashes.is_nan() || hashes <= 1.0 { return Difficulty(0) };
+    Difficulty(hashes.log2().round().min(Self::MAX.0 as f64) as u32)
+  }
+
+  /// The average number of hashes needed to find a block.
+  ///
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::difficulty::Difficulty;
+  /// assert_eq!(Difficulty::DEMO.expected_hashes(), 256.0);
+  /// ```
+  pub fn expected_hashes(&self) -> f64 {
+    2f64.powi(self.0 as i32)
+  }
+
+  /// The work a block at this difficulty proves, as its expected number of hashes.
+  /// Saturates at `u128::MAX` from 128 bits up.
+  ///
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::difficulty::Difficulty;
+  /// assert_eq!(Difficulty::MEDIUM.work(), 65536);
+  /// assert_eq!(Difficulty(200).work(), u128::MAX);
+  /// ```
+  pub fn work(&self) -> u128 {
+    1u128.checked_shl(self.0).unwrap_or(u128::MAX)
+  }
+
+  /// Returns `true` if `hash` has at least the required leading zero bits.
+  /// Returns `false` otherwise, including if `hash` is not hex.
+  ///
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::difficulty::Difficulty;
+  /// assert!(Difficulty::MEDIUM.is_met_by("0000ff"));
+  /// assert!(!Difficulty::MEDIUM.is_met_by("0001ff"));
+  /// assert!(!Difficulty::MEDIUM.is_met_by("not a hash"));
+  /// ```
+  pub fn is_met_by(&self, hash: &str) -> bool {
+    leading_zero_bits(hash).is_some_and(|bits| bits >= self.0)
+  }
+}
+
+impl Default for Difficulty {
+  fn default() -> Self {
+    Self::MEDIUM
+  }
+}
+
+/// Estimates the average time to mine a block at `hashrate` hashes per second.
+///
+/// # Examples
+/// ```
+/// # use std::time::Duration;
+/// # use simple_blockchain::difficulty::{ Difficulty, estimated_time_to_mine };
+/// assert_eq!(estimated_time_to_mine(&Difficulty::DEMO, 128.0), Duration::from_secs(2));
+/// assert_eq!(estimated_time_to_mine(&Difficulty::DEMO, 0.0), Duration::MAX);
+/// ```
+pub fn estimated_time_to_mine(difficulty: &Difficulty, hashrate: f64) -> Duration {
+  Duration::try_from_secs_f64(difficulty.expected_hashes() / hashrate).unwrap_or(Duration::MAX)
+}
+
+/// Parses a hex hash as a 256-bit big-endian number. Returns `None` if it isn't 32 bytes of hex.
+///
+/// # Examples
+/// ```
+/// # use simple_blockchain::difficulty::hash_to_u256;
+/// let hash = hash_to_u256(&format!("{}01", "00".repeat(31))).unwrap();
+/// assert_eq!(hash[31], 1);
+/// assert_eq!(hash_to_u256("00ff"), None);
+/// ```
+pub fn hash_to_u256(hash: &str) -> Option<[u8; 32]> {
+  hex::decode(hash).ok()?.try_into().ok()
+}
+
+/// The largest 256-bit hash that meets `difficulty`: its leading bits zero, the rest one.
+///
+/// # Examples
+/// ```
+/// # use simple_blockchain::difficulty::{ Difficulty, target_for };
+/// let target = target_for(Difficulty(12));
+/// assert_eq!(target[..3], [0x00, 0x0f, 0xff]);
+/// assert_eq!(target_for(Difficulty(0)), [0xff; 32]);
+/// ```
+pub fn target_for(difficulty: Difficulty) -> [u8; 32] {
+  let mut target = [0xff; 32];
+  for (index, byte) in target.iter_mut().enumerate() {
+    let zero_bits = difficulty.0.saturating_sub(8 * index as u32).min(8);
+    *byte = 0xffu8.checked_shr(zero_bits).unwrap_or(0);
+  }
+  target
+}
+
+/// How many bits of value a hash is above the threshold for `difficulty`, i.e. log2(hash) minus (256 minus bits).
+/// Negative when the hash meets the difficulty, and -1 means it had one bit to spare.
+/// Hashes within f64 precision of the target can land on zero; [`Difficulty::is_met_by`] is exact.
+/// Returns `None` if `hash` isn't 32 bytes of hex.
+///
+/// # Examples
+/// ```
+/// # use simple_blockchain::difficulty::{ Difficulty, distance_to_target };
+/// let close = format!("002{}", "0".repeat(61));
+/// assert_eq!(distance_to_target(&close, Difficulty(12)), Some(1.0));
+/// assert!(distance_to_target(&format!("0000{}", "f".repeat(60)), Difficulty(12)).unwrap() < 0.0);
+/// ```
+pub fn distance_to_target(hash: &str, difficulty: Difficulty) -> Option<f64> {
+  let hash = hash_to_u256(hash)?;
+  let threshold = 256.0 - difficulty.0.min(256) as f64;
+  let Some(first) = hash.iter().position(|&byte| byte != 0) else { return Some(f64::NEG_INFINITY) };
+  let leading = &hash[first..(first + 8).min(32)];
+  let value = leading.iter().fold(0u64, |value, &byte| value << 8 | byte as u64);
+  let low_bits = 8 * (32 - first - leading.len());
+  Some((value as f64).log2() + low_bits as f64 - threshold)
+}
+
+#[test]
+fn default_is_the_chains_difficulty() {
+  assert_eq!(Difficulty::default(), Difficulty(16));
+  assert!(Difficulty::default().is_met_by("0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43"));
+  assert!(!Difficulty::default().is_met_by("00f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43"));
+}
+
+#[test]
+fn presets_get_harder() {
+  assert!(Difficulty::DEMO < Difficulty::EASY);
+  assert!(Difficulty::EASY < Difficulty::MEDIUM);
+}
+
+#[test]
+fn target_block_time_round_trips_through_estimate() {
+  let difficulty = Difficulty::from_target_block_time(50_000.0, 30.0);
+  let estimate = estimated_time_to_mine(&difficulty, 50_000.0);
+  assert!(estimate > Duration::from_secs(15) && estimate < Duration::from_secs(60));
+}
+
+#[test]
+fn target_block_time_stays_within_its_bounds() {
+  let extremes = [f64::NAN, f64::NEG_INFINITY, -1.0, 0.0, 0.5, 1.0, 2.0, 1e9, 1e300, f64::MAX, f64::INFINITY];
+  for hashrate in extremes {
+    for seconds in extremes {
+      let difficulty = Difficulty::from_target_block_time(hashrate, seconds);
+      assert!(difficulty <= Difficulty::MAX, "{} H/s for {} s", hashrate, seconds);
+      let hashes = hashrate * seconds;
+      if hashes.is_nan() || hashes <= 1.0 {
+        assert_eq!(difficulty, Difficulty(0), "{} H/s for {} s", hashrate, seconds);
+      } else if hashes < Difficulty::MAX.expected_hashes() {
+        // The nearest power of two, so within a factor of the square root of two.
+        let ratio = difficulty.expected_hashes() / hashes;
+        assert!(ratio >= 0.5f64.sqrt() && ratio <= 2f64.sqrt(), "{} H/s for {} s", hashrate, seconds);
+      }
+    }
+  }
+}
+
+#[test]
+fn target_is_the_largest_hash_that_meets_the_difficulty() {
+  for bits in [0, 7, 8, 9, 16, 255, 256, 300] {
+    let target = hex::encode(target_for(Difficulty(bits)));
+    assert!(Difficulty(bits).is_met_by(&target) || bits > 256, "{} bits", bits);
+    if bits > 0 && bits <= 256 {
+      assert!(distance_to_target(&target, Difficulty(bits)).unwrap() <= 0.0);
+    }
+  }
+  assert_eq!(target_for(Difficulty(300)), [0; 32]);
+  assert_eq!(distance_to_target(&"0".repeat(64), Difficulty::MEDIUM), Some(f64::NEG_INFINITY));
+}
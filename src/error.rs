@@ -4,6 +4,48 @@ use thiserror::Error;
 pub enum BlockchainError {
     #[error("invalid chain length")]
     InvalidChainLength,
-    #[error("invalid block")]
-    InvalidBlock
-}
\ No newline at end of file
+    #[error("invalid block: {0}")]
+    InvalidBlock(#[from] BlockValidationError),
+    #[error("malformed json: {0}")]
+    MalformedJson(String),
+    #[error("checksum mismatch")]
+    ChecksumMismatch,
+    #[error("io error: {0}")]
+    Io(String),
+    #[error("unknown chain: {0}")]
+    UnknownChain(String),
+    #[error("input is {size} bytes, over the limit of {limit}")]
+    InputTooLarge { size: usize, limit: usize },
+    #[error("input is nested more than {limit} levels deep")]
+    InputTooDeep { limit: usize }
+}
+
+/// Why a block failed validation. Each variant belongs to one validation [`Rule`](crate::rules::Rule).
+#[derive(Error, PartialEq, Debug, Clone)]
+pub enum BlockValidationError {
+    #[error("id doesn't follow the previous block's id")]
+    IdOutOfSequence,
+    #[error("previous hash doesn't match the previous block's hash")]
+    PreviousHashMismatch,
+    #[error("block is larger than the maximum block size")]
+    TooLarge,
+    #[error("timestamp is before the previous block's or too far in the future")]
+    InvalidTimestamp,
+    #[error("hash doesn't meet the difficulty")]
+    DifficultyNotMet,
+    #[error("hash doesn't match the block's contents")]
+    HashMismatch,
+    #[error("block isn't signed by a validator")]
+    InvalidSignature,
+    #[error("{rule}: {reason}")]
+    Custom { rule: String, reason: String }
+}
+
+/// Why arithmetic on an [`Amount`](crate::types::Amount) failed.
+#[derive(Error, PartialEq, Debug, Clone, Copy)]
+pub enum AmountError {
+    #[error("amount overflowed")]
+    Overflow,
+    #[error("amount went below zero")]
+    Underflow
+}
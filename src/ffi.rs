@@ -0,0 +1,264 @@
+//! A C ABI over the core API, enabled by the `capi` feature.
+//!
+//! The declarations are in `include/simple_blockchain.h`.
+//! Blocks and chains cross the boundary as JSON strings.
+//! Strings returned by this module are owned by the caller and must be freed with [`sb_string_free`].
+//! Error codes are the stable [`ErrorStatus`] codes.
+//!
+//! The crate builds as an rlib; build the shared library with
+//! `cargo rustc --release --features capi --crate-type cdylib`.
+use std::ffi::{ c_char, CStr, CString };
+use std::ptr;
+use crate::block::Block;
+use crate::blockchain::Blockchain;
+use crate::decode::{ self, DecodeLimits };
+use crate::difficulty::Difficulty;
+use crate::error::BlockchainError;
+use crate::params::ChainParams;
+use crate::status::ErrorStatus;
+
+/// The call succeeded.
+pub const SB_OK: i32 = 0;
+/// A pointer argument was null.
+pub const SB_ERR_NULL: i32 = -1;
+/// A string argument wasn't valid UTF-8.
+pub const SB_ERR_UTF8: i32 = -2;
+/// See [`BlockchainError::InvalidChainLength`].
+pub const SB_ERR_INVALID_CHAIN_LENGTH: i32 = 1;
+/// See [`BlockchainError::MalformedJson`].
+pub const SB_ERR_MALFORMED_JSON: i32 = 2;
+/// See [`BlockchainError::ChecksumMismatch`].
+pub const SB_ERR_CHECKSUM_MISMATCH: i32 = 3;
+/// See [`BlockchainError::Io`].
+pub const SB_ERR_IO: i32 = 4;
+/// See [`BlockchainError::UnknownChain`].
+pub const SB_ERR_UNKNOWN_CHAIN: i32 = 5;
+/// See [`BlockchainError::InputTooLarge`].
+pub const SB_ERR_INPUT_TOO_LARGE: i32 = 6;
+/// See [`BlockchainError::InputTooDeep`].
+pub const SB_ERR_INPUT_TOO_DEEP: i32 = 7;
+/// See [`BlockValidationError::IdOutOfSequence`].
+pub const SB_ERR_ID_OUT_OF_SEQUENCE: i32 = 10;
+/// See [`BlockValidationError::PreviousHashMismatch`].
+pub const SB_ERR_PREVIOUS_HASH_MISMATCH: i32 = 11;
+/// See [`BlockValidationError::TooLarge`].
+pub const SB_ERR_TOO_LARGE: i32 = 12;
+/// See [`BlockValidationError::InvalidTimestamp`].
+pub const SB_ERR_INVALID_TIMESTAMP: i32 = 13;
+/// See [`BlockValidationError::DifficultyNotMet`].
+pub const SB_ERR_DIFFICULTY_NOT_MET: i32 = 14;
+/// See [`BlockValidationError::HashMismatch`].
+pub const SB_ERR_HASH_MISMATCH: i32 = 15;
+/// See [`BlockValidationError::Custom`].
+pub const SB_ERR_CUSTOM_RULE: i32 = 16;
+/// See [`BlockValidationError::InvalidSignature`].
+pub const SB_ERR_INVALID_SIGNATURE: i32 = 17;
+
+/// The hardest difficulty the C ABI accepts, about four billion hashes per block.
+/// Mining can't be interrupted from C, so harder chains would hang the caller in [`sb_mine_block`].
+pub const SB_MAX_DIFFICULTY: u32 = 32;
+
+/// An opaque handle to a blockchain.
+pub struct SbBlockchain(Blockchain<Block>);
+
+/// Creates a blockchain at `difficulty` leading zero bits, with its genesis block mined.
+/// Returns null if `difficulty` is over [`SB_MAX_DIFFICULTY`].
+/// Free it with [`sb_blockchain_free`].
+#[no_mangle]
+pub extern "C" fn sb_blockchain_new(difficulty: u32) -> *mut SbBlockchain {
+  let Some(params) = params_at(difficulty) else { return ptr::null_mut() };
+  let mut chain = Blockchain::with_params(params);
+  // Can't fail on a new chain, but a panic mustn't unwind into the caller.
+  if chain.genesis().is_err() { return ptr::null_mut() };
+  Box::into_raw(Box::new(SbBlockchain(chain)))
+}
+
+/// Frees a blockchain. Does nothing if `chain` is null.
+///
+/// # Safety
+/// `chain` must be null or come from this module, and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn sb_blockchain_free(chain: *mut SbBlockchain) {
+  if !chain.is_null() { drop(Box::from_raw(chain)) };
+}
+
+/// Mines the next block with `data` at the difficulty the chain requires, without adding it.
+/// Returns the block's JSON, or null if an argument is null or not UTF-8.
+///
+/// # Safety
+/// `chain` must be null or a live handle, and `data` null or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn sb_mine_block(chain: *const SbBlockchain, data: *const c_char) -> *mut c_char {
+  let (Some(chain), Ok(data)) = (chain.as_ref(), read_str(data)) else { return ptr::null_mut() };
+  let Ok(template) = chain.0.block_template(data.to_string()) else { return ptr::null_mut() };
+  to_c_string(serde_json::to_string(&template.mine()).unwrap())
+}
+
+/// Validates the block in `block_json` and adds it to the chain.
+/// Returns [`SB_OK`] or an `SB_ERR_*` code.
+///
+/// # Safety
+/// `chain` must be null or a live handle, and `block_json` null or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn sb_add_block(chain: *mut SbBlockchain, block_json: *const c_char) -> i32 {
+  let Some(chain) = chain.as_mut() else { return SB_ERR_NULL };
+  match read_str(block_json) {
+    Ok(block_json) => chain.0.submit_mined(block_json).map_or_else(|error| error_code(&error), |_| SB_OK),
+    Err(code) => code,
+  }
+}
+
+/// Returns 1 if every block in the chain is valid, 0 if not, or [`SB_ERR_NULL`].
+/// Like [`Blockchain::is_chain_valid`], a chain with only its genesis block isn't valid.
+///
+/// # Safety
+/// `chain` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn sb_validate(chain: *const SbBlockchain) -> i32 {
+  match chain.as_ref() {
+    Some(chain) => chain.0.is_chain_valid() as i32,
+    None => SB_ERR_NULL,
+  }
+}
+
+/// Returns the chain's blocks as a JSON array, or null if `chain` is null.
+///
+/// # Safety
+/// `chain` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn sb_blockchain_to_json(chain: *const SbBlockchain) -> *mut c_char {
+  match chain.as_ref() {
+    Some(chain) => to_c_string(serde_json::to_string(&chain.0.blocks).unwrap()),
+    None => ptr::null_mut(),
+  }
+}
+
+/// Loads a chain at `difficulty` from a JSON array of blocks, as written by [`sb_blockchain_to_json`].
+/// Returns null if `difficulty` is over [`SB_MAX_DIFFICULTY`], if the JSON isn't an array of blocks within [`DecodeLimits::for_chain`],
+/// or if it has no blocks or any of them is invalid, checked like [`Blockchain::restore`].
+///
+/// # Safety
+/// `blocks_json` must be null or a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn sb_blockchain_from_json(blocks_json: *const c_char, difficulty: u32) -> *mut SbBlockchain {
+  let Some(params) = params_at(difficulty) else { return ptr::null_mut() };
+  let Ok(blocks_json) = read_str(blocks_json) else { return ptr::null_mut() };
+  let Ok(blocks) = decode::from_json::<Vec<Block>>(blocks_json, DecodeLimits::for_chain()) else { return ptr::null_mut() };
+  let chain = Blockchain::with_params(params);
+  let Some(genesis) = blocks.first() else { return ptr::null_mut() };
+  if chain.validate_genesis(genesis).is_err() { return ptr::null_mut() };
+  if blocks.windows(2).any(|pair| chain.validate_block(&pair[1], &pair[0]).is_err()) { return ptr::null_mut() };
+  Box::into_raw(Box::new(SbBlockchain(Blockchain { blocks, ..chain })))
+}
+
+/// Frees a string returned by this module. Does nothing if `string` is null.
+///
+/// # Safety
+/// `string` must be null or come from this module, and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn sb_string_free(string: *mut c_char) {
+  if !string.is_null() { drop(CString::from_raw(string)) };
+}
+
+unsafe fn read_str<'a>(string: *const c_char) -> Result<&'a str, i32> {
+  if string.is_null() { return Err(SB_ERR_NULL) };
+  CStr::from_ptr(string).to_str().map_err(|_| SB_ERR_UTF8)
+}
+
+// Block data can't hold a NUL byte on the C side, so any JSON we produce is NUL-free.
+fn params_at(difficulty: u32) -> Option<ChainParams> {
+  (difficulty <= SB_MAX_DIFFICULTY).then(|| ChainParams { difficulty: Difficulty(difficulty), ..Default::default() })
+}
+
+fn to_c_string(string: String) -> *mut c_char {
+  CString::new(string).unwrap().into_raw()
+}
+
+// The codes are the stable ones from ErrorStatus, which the SB_ERR_* constants mirror.
+fn error_code(error: &BlockchainError) -> i32 {
+  ErrorStatus::from(error).code
+}
+
+#[test]
+fn mines_adds_and_round_trips_through_the_c_abi() {
+  unsafe {
+    let chain = sb_blockchain_new(8);
+    let data = CString::new("next").unwrap();
+    let block = sb_mine_block(chain, data.as_ptr());
+    assert!(!block.is_null());
+    assert_eq!(sb_validate(chain), 0);
+    assert_eq!(sb_add_block(chain, block), SB_OK);
+    assert_eq!(sb_add_block(chain, block), SB_ERR_ID_OUT_OF_SEQUENCE);
+    assert_eq!(sb_validate(chain), 1);
+    sb_string_free(block);
+
+    let json = sb_blockchain_to_json(chain);
+    let restored = sb_blockchain_from_json(json, 8);
+    assert_eq!((*restored).0.blocks, (*chain).0.blocks);
+    sb_string_free(json);
+    sb_blockchain_free(restored);
+    sb_blockchain_free(chain);
+  }
+}
+
+#[test]
+fn rejects_bad_arguments() {
+  unsafe {
+    let garbage = CString::new("not json").unwrap();
+    assert_eq!(sb_add_block(ptr::null_mut(), garbage.as_ptr()), SB_ERR_NULL);
+    assert_eq!(sb_validate(ptr::null()), SB_ERR_NULL);
+    assert!(sb_mine_block(ptr::null(), garbage.as_ptr()).is_null());
+    assert!(sb_blockchain_from_json(garbage.as_ptr(), 8).is_null());
+    assert!(sb_blockchain_new(SB_MAX_DIFFICULTY + 1).is_null());
+    assert!(sb_blockchain_new(256).is_null());
+    let empty = CString::new("[]").unwrap();
+    assert!(sb_blockchain_from_json(empty.as_ptr(), 8).is_null());
+    assert!(sb_blockchain_from_json(empty.as_ptr(), u32::MAX).is_null());
+
+    let chain = sb_blockchain_new(8);
+    assert_eq!(sb_add_block(chain, ptr::null()), SB_ERR_NULL);
+    assert_eq!(sb_add_block(chain, garbage.as_ptr()), SB_ERR_MALFORMED_JSON);
+    sb_blockchain_free(chain);
+  }
+}
+
+#[test]
+fn header_declares_every_export() {
+  let header = include_str!("../include/simple_blockchain.h");
+  for name in [
+    "sb_blockchain_new", "sb_blockchain_free", "sb_mine_block", "sb_add_block", "sb_validate",
+    "sb_blockchain_to_json", "sb_blockchain_from_json", "sb_string_free",
+  ] {
+    assert!(header.contains(&format!("{}(", name)), "{} is missing from the header", name);
+  }
+  for (name, code) in [
+    ("SB_MAX_DIFFICULTY", SB_MAX_DIFFICULTY as i32), ("SB_OK", SB_OK), ("SB_ERR_NULL", SB_ERR_NULL), ("SB_ERR_UTF8", SB_ERR_UTF8),
+    ("SB_ERR_INVALID_CHAIN_LENGTH", SB_ERR_INVALID_CHAIN_LENGTH), ("SB_ERR_MALFORMED_JSON", SB_ERR_MALFORMED_JSON),
+    ("SB_ERR_ID_OUT_OF_SEQUENCE", SB_ERR_ID_OUT_OF_SEQUENCE), ("SB_ERR_PREVIOUS_HASH_MISMATCH", SB_ERR_PREVIOUS_HASH_MISMATCH),
+    ("SB_ERR_TOO_LARGE", SB_ERR_TOO_LARGE), ("SB_ERR_INVALID_TIMESTAMP", SB_ERR_INVALID_TIMESTAMP),
+    ("SB_ERR_DIFFICULTY_NOT_MET", SB_ERR_DIFFICULTY_NOT_MET), ("SB_ERR_HASH_MISMATCH", SB_ERR_HASH_MISMATCH),
+    ("SB_ERR_CUSTOM_RULE", SB_ERR_CUSTOM_RULE), ("SB_ERR_CHECKSUM_MISMATCH", SB_ERR_CHECKSUM_MISMATCH),
+    ("SB_ERR_IO", SB_ERR_IO), ("SB_ERR_UNKNOWN_CHAIN", SB_ERR_UNKNOWN_CHAIN),
+    ("SB_ERR_INPUT_TOO_LARGE", SB_ERR_INPUT_TOO_LARGE), ("SB_ERR_INPUT_TOO_DEEP", SB_ERR_INPUT_TOO_DEEP),
+    ("SB_ERR_INVALID_SIGNATURE", SB_ERR_INVALID_SIGNATURE),
+  ] {
+    assert!(header.contains(&format!("#define {} {}\n", name, code)), "{} doesn't match the header", name);
+  }
+}
+
+#[test]
+fn constants_mirror_the_stable_error_codes() {
+  use crate::error::BlockValidationError::*;
+  assert_eq!(error_code(&BlockchainError::ChecksumMismatch), SB_ERR_CHECKSUM_MISMATCH);
+  assert_eq!(error_code(&BlockchainError::Io(String::new())), SB_ERR_IO);
+  assert_eq!(error_code(&BlockchainError::UnknownChain(String::new())), SB_ERR_UNKNOWN_CHAIN);
+  assert_eq!(error_code(&BlockchainError::InvalidChainLength), SB_ERR_INVALID_CHAIN_LENGTH);
+  for (error, code) in [
+    (IdOutOfSequence, SB_ERR_ID_OUT_OF_SEQUENCE), (PreviousHashMismatch, SB_ERR_PREVIOUS_HASH_MISMATCH),
+    (TooLarge, SB_ERR_TOO_LARGE), (InvalidTimestamp, SB_ERR_INVALID_TIMESTAMP),
+    (DifficultyNotMet, SB_ERR_DIFFICULTY_NOT_MET), (HashMismatch, SB_ERR_HASH_MISMATCH),
+    (Custom { rule: String::new(), reason: String::new() }, SB_ERR_CUSTOM_RULE),
+  ] {
+    assert_eq!(error_code(&error.into()), code);
+  }
+}
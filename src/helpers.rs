@@ -1,16 +1,18 @@
 use sha2::{Sha256, Digest};
+use serde_json::Value;
+use crate::difficulty::Difficulty;
 
-pub const PREFIX: &str = "00";
-
-pub fn binary_string_of(hash: &String) -> String {
-  hex::decode(hash)
-      .unwrap()
-      .into_iter()
-      .map(|num| format!("{:b}", num))
-      .collect::<String>()
+pub fn leading_zero_bits(hash: &str) -> Option<u32> {
+  let bytes = hex::decode(hash).ok()?;
+  let mut bits = 0;
+  for byte in bytes {
+    bits += byte.leading_zeros();
+    if byte != 0 { break };
+  }
+  Some(bits)
 }
 
-pub fn calculate_hash(id: u64, timestamp: i64, previous_hash: &str, data: &str, nonce: u64) -> String {
+pub fn hash_preimage(id: u64, timestamp: i64, previous_hash: &str, data: &Value, nonce: u64) -> Vec<u8> {
   let content = serde_json::json!({
     "id": id,
     "timestamp": timestamp,
@@ -18,18 +20,21 @@ pub fn calculate_hash(id: u64, timestamp: i64, previous_hash: &str, data: &str,
     "data": data,
     "nonce": nonce
   });
+  content.to_string().into_bytes()
+}
+
+pub fn calculate_hash(id: u64, timestamp: i64, previous_hash: &str, data: &Value, nonce: u64) -> String {
   let mut hasher = Sha256::new();
-  hasher.update(content.to_string().as_bytes());
-  hex::encode(hasher.finalize().as_slice().to_owned())
+  hasher.update(hash_preimage(id, timestamp, previous_hash, data, nonce));
+  hex::encode(hasher.finalize())
 }
 
-pub fn mine_hash(id: u64, timestamp: i64, previous_hash: &str, data: &str) -> (u64, String) {
+pub fn mine_hash(id: u64, timestamp: i64, previous_hash: &str, data: &Value, difficulty: Difficulty) -> (u64, String) {
   let mut nonce = 0;
 
   loop {
     let hash = calculate_hash(id, timestamp, previous_hash, data, nonce);
-    let binary_hash = binary_string_of(&hash);
-    if binary_hash.starts_with(PREFIX) {
+    if difficulty.is_met_by(&hash) {
       return (nonce, hash);
     }
     nonce += 1;
@@ -37,10 +42,21 @@ pub fn mine_hash(id: u64, timestamp: i64, previous_hash: &str, data: &str) -> (u
 }
 
 #[test]
-fn converts_hash_to_binary_string() {
-  let hash = String::from("ff");
+fn counts_leading_zero_bits() {
+  assert_eq!(leading_zero_bits("ff"), Some(0));
+  assert_eq!(leading_zero_bits("0000ff"), Some(16));
+  assert_eq!(leading_zero_bits("00001f"), Some(19));
+  assert_eq!(leading_zero_bits("0000"), Some(16));
+  assert_eq!(leading_zero_bits("not_a_hash"), None);
+}
 
-  assert_eq!(binary_string_of(&hash), String::from("11111111"));
+#[test]
+fn builds_hash_preimage_with_sorted_keys() {
+  let preimage = hash_preimage(1, 1643223669, "genesis", &"next".into(), 7);
+  assert_eq!(
+    String::from_utf8(preimage).unwrap(),
+    r#"{"data":"next","id":1,"nonce":7,"previous_hash":"genesis","timestamp":1643223669}"#
+  );
 }
 
 #[test]
@@ -49,7 +65,7 @@ fn calculates_hash() {
     69,
     1643220097,
     "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43",
-    "foo",
+    &"foo".into(),
     9386
   );
   assert_eq!(hash, "00007751f1b92a8ac1bdc88407e7a85b4c0dd59313d8fa78ae2208dbcaaad604".to_string());
@@ -61,7 +77,8 @@ fn mines_hash() {
     69,
     1643220097,
     "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43",
-    "foo"
+    &"foo".into(),
+    Difficulty::MEDIUM
   );
   assert_eq!(nonce, 9386);
   assert_eq!(hash, "00007751f1b92a8ac1bdc88407e7a85b4c0dd59313d8fa78ae2208dbcaaad604".to_string());
@@ -4,7 +4,28 @@
 pub use self::block::Block;
 pub use self::blockchain::Blockchain;
 
+pub mod backup;
 pub mod block;
 pub mod blockchain;
+pub mod builder;
+pub mod compare;
+pub mod compat;
+pub mod crypto;
+pub mod decode;
+pub mod difficulty;
 pub mod error;
+#[cfg(feature = "capi")]
+pub mod ffi;
+pub mod migrate;
+pub mod miner;
+pub mod orphans;
+pub mod params;
+pub mod registry;
+pub mod relay;
+pub mod rules;
+pub mod status;
+pub mod template;
+pub mod timestamp;
+pub mod transaction;
+pub mod types;
 mod helpers;
\ No newline at end of file
@@ -0,0 +1,150 @@
+//! Upgrading chains saved by older versions of the crate.
+//!
+//! Older versions wrote a chain as a JSON array of blocks with the same snake_case fields as [`Block`],
+//! timestamps in Unix seconds and lowercase hex hashes. They checked each block's id, link, proof of work and hash,
+//! but not its timestamp or size. [`upgrade_chain`] reads such a chain, or any shape [`compat`] accepts,
+//! checks that the blocks still form a valid chain under those [`legacy_rules`], and writes them back as [`Block`] JSON.
+//!
+//! The output is the same plain array of blocks that [`Blockchain::backup`] and the C bindings read and write,
+//! so an upgraded chain can be restored directly.
+use std::io::{ Read, Write };
+use std::sync::Arc;
+use crate::block::Block;
+use crate::blockchain::Blockchain;
+use crate::compat;
+use crate::decode::DecodeLimits;
+use crate::error::{ BlockchainError, BlockchainError::* };
+use crate::params::ChainParams;
+use crate::rules::{ HashMatch, IdSequence, Linkage, ProofOfWork, Rule };
+
+/// The rules older versions checked: everything but the [`Timestamp`](crate::rules::Timestamp)
+/// and [`BlockSize`](crate::rules::BlockSize) rules added since.
+pub fn legacy_rules() -> Vec<Arc<dyn Rule>> {
+  vec![
+    Arc::new(IdSequence),
+    Arc::new(Linkage),
+    Arc::new(ProofOfWork),
+    Arc::new(HashMatch),
+  ]
+}
+
+/// Upgrades a chain under the default [`ChainParams`] with the [`legacy_rules`], so every chain an older version
+/// accepted upgrades, even if today's rules would reject some of its blocks. Returns how many blocks were written.
+///
+/// # Examples
+/// ```
+/// # use simple_blockchain::block::Block;
+/// # use simple_blockchain::blockchain::Blockchain;
+/// # use simple_blockchain::migrate::upgrade_chain;
+/// # let mut my_blockchain = Blockchain::new();
+/// # my_blockchain.genesis().unwrap();
+/// # let block = Block::new(1, &my_blockchain.blocks[0].hash, "next".to_string());
+/// # my_blockchain.add_block(block).unwrap();
+/// let legacy = serde_json::to_string(&my_blockchain.blocks).unwrap();
+/// let mut upgraded = Vec::new();
+/// assert_eq!(upgrade_chain(legacy.as_bytes(), &mut upgraded), Ok(2));
+/// assert_eq!(serde_json::from_slice::<Vec<Block>>(&upgraded).unwrap(), my_blockchain.blocks);
+/// ```
+///
+/// # Errors
+/// Returns the same errors as [`upgrade_chain_with`].
+pub fn upgrade_chain(reader: impl Read, writer: impl Write) -> Result<usize, BlockchainError> {
+  upgrade_chain_with(&ChainParams { rules: legacy_rules(), ..Default::default() }, reader, writer)
+}
+
+/// Upgrades a chain, validating its blocks under `params`. Nothing is written unless every block is valid.
+///
+/// # Errors
+/// Returns [`BlockchainError::Io`] if reading or writing fails,
+/// [`BlockchainError::InputTooLarge`] or [`BlockchainError::InputTooDeep`] if the chain is over [`DecodeLimits::for_chain`],
+/// [`BlockchainError::MalformedJson`] if it isn't an array of blocks in any shape older versions wrote,
+/// [`BlockchainError::InvalidChainLength`] if it has no blocks,
+/// and [`BlockchainError::InvalidBlock`] for the first invalid block, checked like [`Blockchain::restore`].
+pub fn upgrade_chain_with(params: &ChainParams, reader: impl Read, mut writer: impl Write) -> Result<usize, BlockchainError> {
+  let limit = DecodeLimits::for_chain().max_bytes;
+  // One byte past the limit is enough to tell that the input is too large, without reading all of it.
+  let mut json = String::new();
+  reader.take(limit as u64 + 1).read_to_string(&mut json).map_err(|error| Io(error.to_string()))?;
+  let blocks = compat::chain_from_tutorial_json(&json)?;
+  if blocks.is_empty() { return Err(InvalidChainLength) };
+
+  let chain = Blockchain::<Block>::with_params(params.clone());
+  chain.validate_genesis(&blocks[0])?;
+  for pair in blocks.windows(2) {
+    chain.validate_block(&pair[1], &pair[0])?;
+  }
+  serde_json::to_writer(&mut writer, &blocks).map_err(|error| Io(error.to_string()))?;
+  writer.flush().map_err(|error| Io(error.to_string()))?;
+  Ok(blocks.len())
+}
+
+#[test]
+fn upgrades_any_shape_compat_reads() {
+  let legacy = r#"[
+    {"id":0,"hash":"0000DBEB9E573D5382C63FD9A222C3720A4341B06416348FC5BBC0D19380A248","previousHash":"genesis","timestamp":"2022-01-26T18:50:00Z","data":"genesis!","nonce":44475},
+    {"id":1,"hash":"0000CC07887FB749C99974E8E93DEBB64E205086F6D0962EF17BF6F0BB295F3E","previousHash":"0000DBEB9E573D5382C63FD9A222C3720A4341B06416348FC5BBC0D19380A248","timestamp":1643223669,"data":"next","nonce":236492}
+  ]"#;
+  let mut upgraded = Vec::new();
+  assert_eq!(upgrade_chain(legacy.as_bytes(), &mut upgraded), Ok(2));
+
+  let blocks: Vec<Block> = serde_json::from_slice(&upgraded).unwrap();
+  assert_eq!(blocks[0].timestamp, 1643223000);
+  assert_eq!(blocks[1].previous_hash, blocks[0].hash);
+  assert!(Blockchain::<Block> { blocks, params: ChainParams::default() }.is_chain_valid());
+}
+
+#[test]
+fn writes_nothing_for_an_invalid_chain() {
+  use crate::error::BlockValidationError;
+  let tampered = r#"[
+    {"id":0,"hash":"0000dbeb9e573d5382c63fd9a222c3720a4341b06416348fc5bbc0d19380a248","previous_hash":"genesis","timestamp":1643223000,"data":"genesis!","nonce":44475},
+    {"id":1,"hash":"0000cc07887fb749c99974e8e93debb64e205086f6d0962ef17bf6f0bb295f3e","previous_hash":"0000dbeb9e573d5382c63fd9a222c3720a4341b06416348fc5bbc0d19380a248","timestamp":1643223669,"data":"tampered","nonce":236492}
+  ]"#;
+  let mut upgraded = Vec::new();
+  assert_eq!(upgrade_chain(tampered.as_bytes(), &mut upgraded), Err(InvalidBlock(BlockValidationError::HashMismatch)));
+  assert_eq!(upgrade_chain("[]".as_bytes(), &mut upgraded), Err(InvalidChainLength));
+  assert!(upgraded.is_empty());
+}
+
+#[test]
+fn checks_the_genesis_block() {
+  use crate::error::BlockValidationError;
+  use crate::rules::Rule;
+  #[derive(Debug)]
+  struct NoGenesis;
+
+  impl Rule for NoGenesis {
+    fn name(&self) -> &str { "no-genesis" }
+
+    fn check(&self, _: &Block, _: &Block, _: &ChainParams) -> Result<(), BlockValidationError> {
+      Ok(())
+    }
+
+    fn check_genesis(&self, _: &Block, _: &ChainParams) -> Result<(), BlockValidationError> {
+      Err(BlockValidationError::Custom { rule: self.name().to_string(), reason: "no genesis".to_string() })
+    }
+  }
+
+  let legacy = r#"[{"id":0,"hash":"0000dbeb9e573d5382c63fd9a222c3720a4341b06416348fc5bbc0d19380a248","previous_hash":"genesis","timestamp":1643223000,"data":"genesis!","nonce":44475}]"#;
+  let mut upgraded = Vec::new();
+  assert!(matches!(
+    upgrade_chain_with(&ChainParams::default().with_rule(NoGenesis), legacy.as_bytes(), &mut upgraded),
+    Err(InvalidBlock(BlockValidationError::Custom { .. }))
+  ));
+  assert!(upgraded.is_empty());
+}
+
+#[test]
+fn upgrades_chains_that_only_older_rules_accept() {
+  use crate::error::BlockValidationError;
+  use crate::template::BlockTemplate;
+  use crate::types::BlockId;
+  let genesis = BlockTemplate { id: BlockId(0), previous_hash: "genesis".into(), timestamp: 1643223669, data: "genesis!".to_string(), difficulty: Default::default() }.mine();
+  let earlier = BlockTemplate { id: BlockId(1), previous_hash: genesis.hash.clone(), timestamp: 1643223000, data: "next".to_string(), difficulty: Default::default() }.mine();
+  let legacy = serde_json::to_string(&[genesis, earlier]).unwrap();
+
+  let mut upgraded = Vec::new();
+  assert_eq!(upgrade_chain_with(&ChainParams::default(), legacy.as_bytes(), &mut upgraded), Err(InvalidBlock(BlockValidationError::InvalidTimestamp)));
+  assert_eq!(upgrade_chain(legacy.as_bytes(), &mut upgraded), Ok(2));
+  assert_eq!(serde_json::from_slice::<serde_json::Value>(&upgraded).unwrap(), serde_json::from_str::<serde_json::Value>(&legacy).unwrap());
+}
@@ -0,0 +1,538 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{ AtomicBool, AtomicU64, Ordering };
+use std::thread;
+use std::time::{ Duration, Instant };
+use serde::{ Serialize, Deserialize };
+use crate::block::{ Block, BlockData };
+use crate::decode::{ self, DecodeLimits };
+use crate::difficulty::{ Difficulty, estimated_time_to_mine };
+use crate::helpers::*;
+use crate::template::BlockTemplate;
+
+/// Mines block templates, each at the [`BlockTemplate::difficulty`] it carries.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Miner;
+
+/// How many nonces each worker thread claims at a time in [`Miner::mine_parallel`].
+pub const CHUNK_SIZE: u64 = 1024;
+
+/// What one worker thread did during [`Miner::mine_parallel`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct WorkerStats {
+  pub hashes: u64,
+  pub chunks: u64,
+}
+
+/// An interrupted search for a block's nonce, which can be saved to disk and picked up later.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MiningState<T = String> {
+  /// The template being mined, at its [`BlockTemplate::difficulty`].
+  pub template: BlockTemplate<T>,
+  /// Every nonce below this one has been tried, by one thread or by all the workers of a parallel search.
+  pub next_nonce: u64,
+}
+
+/// A sample of the hashes tried while mining, from [`Miner::mine_traced`].
+///
+/// Every `every`th attempt is recorded, and only the latest `capacity` samples are kept.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MiningTrace {
+  pub every: u64,
+  pub capacity: usize,
+  /// The number of hashes tried across every traced search.
+  pub attempts: u64,
+  pub samples: VecDeque<HashSample>,
+}
+
+/// One recorded attempt in a [`MiningTrace`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HashSample {
+  pub nonce: u64,
+  pub hash: String,
+}
+
+/// How fast this machine hashes, as measured by [`Miner::benchmark`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HashrateReport {
+  pub hashes: u64,
+  pub elapsed: Duration,
+}
+
+impl Miner {
+  /// Mines a template into a block at its difficulty.
+  ///
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::difficulty::Difficulty;
+  /// # use simple_blockchain::miner::Miner;
+  /// # use simple_blockchain::template::BlockTemplate;
+  /// # use simple_blockchain::types::BlockId;
+  /// let template = BlockTemplate {
+  ///   id: BlockId(1),
+  ///   previous_hash: "genesis".into(),
+  ///   timestamp: 1643223669,
+  ///   data: "next".to_string(),
+  ///   difficulty: Difficulty::DEMO
+  /// };
+  /// let block = Miner.mine(template);
+  /// assert!(Difficulty::DEMO.is_met_by(&block.hash));
+  /// ```
+  pub fn mine<T: BlockData>(&self, template: BlockTemplate<T>) -> Block<T> {
+    let (nonce, hash) = mine_hash(template.id.0, template.timestamp, &template.previous_hash, &template.data.canonical(), template.difficulty);
+    template.seal(nonce, hash)
+  }
+
+  /// Starts a search that can be run a few attempts at a time with [`MiningState::mine_for`].
+  ///
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::difficulty::Difficulty;
+  /// # use simple_blockchain::miner::Miner;
+  /// # use simple_blockchain::template::BlockTemplate;
+  /// # use simple_blockchain::types::BlockId;
+  /// # let template = BlockTemplate {
+  /// #   id: BlockId(1),
+  /// #   previous_hash: "genesis".into(),
+  /// #   timestamp: 1643223669,
+  /// #   data: "next".to_string(),
+  /// #   difficulty: Difficulty::DEMO
+  /// # };
+  /// let state = Miner.start(template);
+  /// assert_eq!(state.next_nonce, 0);
+  /// assert_eq!(state.template.difficulty, Difficulty::DEMO);
+  /// ```
+  pub fn start<T: BlockData>(&self, template: BlockTemplate<T>) -> MiningState<T> {
+    MiningState { template, next_nonce: 0 }
+  }
+
+  /// Mines a template across `threads` worker threads.
+  ///
+  /// Workers claim chunks of [`CHUNK_SIZE`] nonces from a shared counter as they go, rather than
+  /// splitting the nonce space up front, so fast cores simply claim more chunks than slow ones.
+  /// The lowest valid nonce wins, so the block is the same one [`Miner::mine`] would find.
+  ///
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::difficulty::Difficulty;
+  /// # use simple_blockchain::miner::Miner;
+  /// # use simple_blockchain::template::BlockTemplate;
+  /// # use simple_blockchain::types::BlockId;
+  /// let template = BlockTemplate {
+  ///   id: BlockId(1),
+  ///   previous_hash: "genesis".into(),
+  ///   timestamp: 1643223669,
+  ///   data: "next".to_string(),
+  ///   difficulty: Difficulty::EASY
+  /// };
+  /// let (block, workers) = Miner.mine_parallel(template.clone(), 4);
+  /// assert_eq!(block, Miner.mine(template));
+  /// assert_eq!(workers.len(), 4);
+  /// ```
+  pub fn mine_parallel<T: BlockData>(&self, template: BlockTemplate<T>, threads: usize) -> (Block<T>, Vec<WorkerStats>) {
+    let search = search_parallel(&template, 0, threads, &AtomicBool::new(false));
+    let (nonce, hash) = search.found.unwrap();
+    (template.seal(nonce, hash), search.workers)
+  }
+
+  /// Mines a template like [`Miner::mine`], recording attempts into `trace` along the way.
+  /// A trace can be reused for several blocks; its samples keep rolling over.
+  ///
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::difficulty::Difficulty;
+  /// # use simple_blockchain::miner::{ Miner, MiningTrace };
+  /// # use simple_blockchain::template::BlockTemplate;
+  /// # use simple_blockchain::types::BlockId;
+  /// # let template = BlockTemplate {
+  /// #   id: BlockId(1),
+  /// #   previous_hash: "genesis".into(),
+  /// #   timestamp: 1643223669,
+  /// #   data: "next".to_string(),
+  /// #   difficulty: Difficulty::DEMO
+  /// # };
+  /// let mut trace = MiningTrace::new(10, 100);
+  /// let block = Miner.mine_traced(template, &mut trace);
+  /// assert_eq!(trace.attempts, block.nonce + 1);
+  /// assert!(trace.samples.iter().all(|sample| sample.nonce % 10 == 0));
+  /// ```
+  pub fn mine_traced<T: BlockData>(&self, template: BlockTemplate<T>, trace: &mut MiningTrace) -> Block<T> {
+    let data = template.data.canonical();
+    let mut nonce = 0;
+
+    loop {
+      let hash = calculate_hash(template.id.0, template.timestamp, &template.previous_hash, &data, nonce);
+      trace.attempts += 1;
+      if nonce % trace.every.max(1) == 0 { trace.record(nonce, &hash) };
+      if template.difficulty.is_met_by(&hash) {
+        return template.seal(nonce, hash);
+      }
+      nonce += 1;
+    }
+  }
+
+  /// Hashes for roughly `duration` and reports how many hashes were done.
+  ///
+  /// # Examples
+  /// ```
+  /// # use std::time::Duration;
+  /// # use simple_blockchain::miner::Miner;
+  /// let report = Miner::benchmark(Duration::from_millis(10));
+  /// assert!(report.hashes > 0);
+  /// assert!(report.elapsed >= Duration::from_millis(10));
+  /// ```
+  pub fn benchmark(duration: Duration) -> HashrateReport {
+    let data = "benchmark".into();
+    let start = Instant::now();
+    let mut hashes = 0;
+
+    loop {
+      for _ in 0..256 {
+        calculate_hash(1, 1643223669, "benchmark", &data, hashes);
+        hashes += 1;
+      }
+      let elapsed = start.elapsed();
+      if elapsed >= duration {
+        return HashrateReport { hashes, elapsed };
+      }
+    }
+  }
+}
+
+impl<T: BlockData> MiningState<T> {
+  /// Tries up to `attempts` more nonces. Returns the block if one of them was valid.
+  ///
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::miner::Miner;
+  /// # use simple_blockchain::template::BlockTemplate;
+  /// # use simple_blockchain::difficulty::Difficulty;
+  /// # use simple_blockchain::types::BlockId;
+  /// # let template = BlockTemplate {
+  /// #   id: BlockId(1),
+  /// #   previous_hash: "genesis".into(),
+  /// #   timestamp: 1643223669,
+  /// #   data: "next".to_string(),
+  /// #   difficulty: Difficulty::MEDIUM
+  /// # };
+  /// let mut state = Miner.start(template.clone());
+  /// assert_eq!(state.mine_for(10), None);
+  /// assert_eq!(state.next_nonce, 10);
+  /// let block = loop {
+  ///   if let Some(block) = state.mine_for(1000) { break block };
+  /// };
+  /// assert_eq!(block, template.mine());
+  /// ```
+  pub fn mine_for(&mut self, attempts: u64) -> Option<Block<T>> {
+    let template = &self.template;
+    let data = template.data.canonical();
+    for nonce in self.next_nonce..self.next_nonce.saturating_add(attempts) {
+      let hash = calculate_hash(template.id.0, template.timestamp, &template.previous_hash, &data, nonce);
+      if template.difficulty.is_met_by(&hash) {
+        self.next_nonce = nonce;
+        return Some(self.template.clone().seal(nonce, hash));
+      }
+    }
+    self.next_nonce = self.next_nonce.saturating_add(attempts);
+    None
+  }
+
+  /// Continues the search across `threads` worker threads, like [`Miner::mine_parallel`], until a block is found
+  /// or `stop` is set. Returns the block if one was found.
+  ///
+  /// Workers check `stop` between chunks and finish the chunk they're on, so every claimed chunk is searched
+  /// and [`MiningState::next_nonce`] moves up to the end of the last one. Saving the state then loses no work.
+  ///
+  /// # Examples
+  /// ```
+  /// # use std::sync::atomic::AtomicBool;
+  /// # use simple_blockchain::miner::{ Miner, CHUNK_SIZE };
+  /// # use simple_blockchain::template::BlockTemplate;
+  /// # use simple_blockchain::difficulty::Difficulty;
+  /// # use simple_blockchain::types::BlockId;
+  /// # let template = BlockTemplate {
+  /// #   id: BlockId(1),
+  /// #   previous_hash: "genesis".into(),
+  /// #   timestamp: 1643223669,
+  /// #   data: "next".to_string(),
+  /// #   difficulty: Difficulty::MEDIUM
+  /// # };
+  /// let mut state = Miner.start(template.clone());
+  /// assert_eq!(state.mine_parallel_until(4, &AtomicBool::new(true)), None);
+  /// assert_eq!(state.next_nonce, 0);
+  /// assert_eq!(state.mine_parallel_until(4, &AtomicBool::new(false)), Some(template.mine()));
+  /// ```
+  pub fn mine_parallel_until(&mut self, threads: usize, stop: &AtomicBool) -> Option<Block<T>> {
+    let search = search_parallel(&self.template, self.next_nonce, threads, stop);
+    match search.found {
+      Some((nonce, hash)) => {
+        self.next_nonce = nonce;
+        Some(self.template.clone().seal(nonce, hash))
+      },
+      None => {
+        self.next_nonce = search.searched_to;
+        None
+      }
+    }
+  }
+
+  /// Writes the search to a file as JSON.
+  ///
+  /// # Errors
+  /// Returns the [`io::Error`] if the file can't be written.
+  pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+    fs::write(path, serde_json::to_string(self).unwrap())
+  }
+
+  /// Reads a search previously written by [`MiningState::save`].
+  ///
+  /// # Errors
+  /// Returns the [`io::Error`] if the file can't be read,
+  /// or one of kind [`io::ErrorKind::InvalidData`] if it isn't a saved search.
+  pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+    let json = fs::read(path)?;
+    decode::from_json(json, DecodeLimits::default()).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+  }
+}
+
+struct ParallelSearch {
+  found: Option<(u64, String)>,
+  searched_to: u64,
+  workers: Vec<WorkerStats>,
+}
+
+// Chunks are claimed in order from `first_nonce` and each claimed chunk is searched to the end unless a lower
+// nonce has already been found, so when `stop` ends the search every nonce below `searched_to` has been tried.
+fn search_parallel<T: BlockData>(template: &BlockTemplate<T>, first_nonce: u64, threads: usize, stop: &AtomicBool) -> ParallelSearch {
+  let data = template.data.canonical();
+  let next_chunk = AtomicU64::new(0);
+  let best_nonce = AtomicU64::new(u64::MAX);
+
+  let workers = thread::scope(|scope| {
+    let handles = (0..threads.max(1)).map(|_| scope.spawn(|| {
+      let mut stats = WorkerStats::default();
+      loop {
+        if stop.load(Ordering::Relaxed) { return stats };
+        let start = first_nonce.saturating_add(next_chunk.fetch_add(1, Ordering::Relaxed).saturating_mul(CHUNK_SIZE));
+        if start >= best_nonce.load(Ordering::Relaxed) { return stats };
+        stats.chunks += 1;
+        for nonce in start..start.saturating_add(CHUNK_SIZE) {
+          if nonce >= best_nonce.load(Ordering::Relaxed) { break };
+          stats.hashes += 1;
+          let hash = calculate_hash(template.id.0, template.timestamp, &template.previous_hash, &data, nonce);
+          if template.difficulty.is_met_by(&hash) {
+            best_nonce.fetch_min(nonce, Ordering::Relaxed);
+            break;
+          }
+        }
+      }
+    })).collect::<Vec<_>>();
+    handles.into_iter().map(|handle| handle.join().unwrap()).collect::<Vec<_>>()
+  });
+
+  let searched_to = first_nonce.saturating_add(workers.iter().map(|worker| worker.chunks).sum::<u64>().saturating_mul(CHUNK_SIZE));
+  let found = Some(best_nonce.into_inner()).filter(|&nonce| nonce != u64::MAX).map(|nonce| {
+    (nonce, calculate_hash(template.id.0, template.timestamp, &template.previous_hash, &data, nonce))
+  });
+  ParallelSearch { found, searched_to, workers }
+}
+
+impl MiningTrace {
+  /// Creates an empty trace that records every `every`th attempt, keeping the latest `capacity`.
+  /// An `every` of 0 records every attempt.
+  pub fn new(every: u64, capacity: usize) -> Self {
+    Self { every, capacity, attempts: 0, samples: VecDeque::with_capacity(capacity) }
+  }
+
+  /// Returns the trace as JSON, for plotting elsewhere.
+  pub fn to_json(&self) -> String {
+    serde_json::to_string(self).unwrap()
+  }
+
+  fn record(&mut self, nonce: u64, hash: &str) {
+    if self.capacity == 0 { return };
+    if self.samples.len() == self.capacity { self.samples.pop_front(); };
+    self.samples.push_back(HashSample { nonce, hash: hash.to_string() });
+  }
+}
+
+impl HashrateReport {
+  /// The measured hashes per second.
+  ///
+  /// # Examples
+  /// ```
+  /// # use std::time::Duration;
+  /// # use simple_blockchain::miner::HashrateReport;
+  /// let report = HashrateReport { hashes: 5000, elapsed: Duration::from_millis(500) };
+  /// assert_eq!(report.hashes_per_second(), 10000.0);
+  /// ```
+  pub fn hashes_per_second(&self) -> f64 {
+    self.hashes as f64 / self.elapsed.as_secs_f64()
+  }
+
+  /// Suggests the difficulty that mines a block every `block_interval` on average at the measured rate.
+  ///
+  /// # Examples
+  /// ```
+  /// # use std::time::Duration;
+  /// # use simple_blockchain::difficulty::Difficulty;
+  /// # use simple_blockchain::miner::HashrateReport;
+  /// let report = HashrateReport { hashes: 65536, elapsed: Duration::from_secs(1) };
+  /// assert_eq!(report.suggest_difficulty(Duration::from_secs(1)), Difficulty(16));
+  /// ```
+  pub fn suggest_difficulty(&self, block_interval: Duration) -> Difficulty {
+    Difficulty::from_target_block_time(self.hashes_per_second(), block_interval.as_secs_f64())
+  }
+
+  /// Estimates the average time to mine a block at `difficulty` at the measured rate.
+  ///
+  /// # Examples
+  /// ```
+  /// # use std::time::Duration;
+  /// # use simple_blockchain::difficulty::Difficulty;
+  /// # use simple_blockchain::miner::HashrateReport;
+  /// let report = HashrateReport { hashes: 256, elapsed: Duration::from_secs(1) };
+  /// assert_eq!(report.estimated_block_time(Difficulty::DEMO), Duration::from_secs(1));
+  /// ```
+  pub fn estimated_block_time(&self, difficulty: Difficulty) -> Duration {
+    estimated_time_to_mine(&difficulty, self.hashes_per_second())
+  }
+}
+
+#[test]
+fn mines_at_configured_difficulty() {
+  let template = BlockTemplate {
+    id: 69.into(),
+    previous_hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
+    timestamp: 1643220097,
+    data: "foo".to_string(),
+    difficulty: Difficulty::MEDIUM
+  };
+  let block = Miner.mine(template.clone());
+  assert_eq!(block, template.clone().mine());
+  assert_eq!(block.nonce, 9386);
+  let easy = Miner.mine(BlockTemplate { difficulty: Difficulty::DEMO, ..template });
+  assert!(Difficulty::DEMO.is_met_by(&easy.hash));
+  assert!(easy.nonce < 9386);
+}
+
+#[test]
+fn traces_the_latest_attempts() {
+  let template = BlockTemplate {
+    id: 69.into(),
+    previous_hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
+    timestamp: 1643220097,
+    data: "foo".to_string(),
+    difficulty: Difficulty::MEDIUM
+  };
+  let mut trace = MiningTrace::new(1, 3);
+  let block = Miner.mine_traced(template.clone(), &mut trace);
+  assert_eq!(block, template.mine());
+  assert_eq!(trace.attempts, 9387);
+  assert_eq!(trace.samples.iter().map(|sample| sample.nonce).collect::<Vec<_>>(), [9384, 9385, 9386]);
+  assert_eq!(trace.samples[2].hash, block.hash.0);
+  assert!(trace.to_json().starts_with(r#"{"every":1,"capacity":3,"attempts":9387,"samples":[{"nonce":9384,"#));
+}
+
+#[test]
+fn suggested_difficulty_matches_benchmark() {
+  let report = Miner::benchmark(Duration::from_millis(50));
+  let difficulty = report.suggest_difficulty(Duration::from_secs(10));
+  let estimate = report.estimated_block_time(difficulty);
+  assert!(estimate > Duration::from_secs(5) && estimate < Duration::from_secs(20));
+}
+
+#[test]
+fn parallel_mining_finds_the_lowest_nonce() {
+  let template = BlockTemplate {
+    id: 69.into(),
+    previous_hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
+    timestamp: 1643220097,
+    data: "foo".to_string(),
+    difficulty: Difficulty::MEDIUM
+  };
+  let (block, workers) = Miner.mine_parallel(template, 3);
+  assert_eq!(block.nonce, 9386);
+  assert_eq!(block.hash, "00007751f1b92a8ac1bdc88407e7a85b4c0dd59313d8fa78ae2208dbcaaad604".to_string());
+  assert!(workers.iter().map(|worker| worker.hashes).sum::<u64>() > 9386);
+}
+
+#[test]
+fn parallel_mining_with_zero_threads_uses_one() {
+  let template = BlockTemplate {
+    id: 1.into(),
+    previous_hash: "genesis".into(),
+    timestamp: 1643223669,
+    data: "next".to_string(),
+    difficulty: Difficulty::DEMO
+  };
+  let (block, workers) = Miner.mine_parallel(template.clone(), 0);
+  assert_eq!(block, Miner.mine(template));
+  assert_eq!(workers.len(), 1);
+}
+
+#[test]
+fn resumes_a_saved_search() {
+  let template = BlockTemplate {
+    id: 69.into(),
+    previous_hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
+    timestamp: 1643220097,
+    data: "foo".to_string(),
+    difficulty: Difficulty::MEDIUM
+  };
+  let path = std::env::temp_dir().join(format!("simple_blockchain_mining_state_{}.json", std::process::id()));
+  let mut state = Miner.start(template);
+  assert_eq!(state.mine_for(5000), None);
+  assert!(state.save(&path).is_ok());
+
+  let mut resumed = MiningState::load(&path).unwrap();
+  let _ = fs::remove_file(&path);
+  assert_eq!(resumed, state);
+  let block = resumed.mine_for(5000).unwrap();
+  assert_eq!(block.nonce, 9386);
+  assert_eq!(block.hash, "00007751f1b92a8ac1bdc88407e7a85b4c0dd59313d8fa78ae2208dbcaaad604".to_string());
+}
+
+#[test]
+fn refuses_to_load_garbage() {
+  let path = std::env::temp_dir().join(format!("simple_blockchain_garbage_{}.json", std::process::id()));
+  fs::write(&path, "garbage").unwrap();
+  let result = MiningState::<String>::load(&path);
+  let _ = fs::remove_file(&path);
+  assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn suspends_and_resumes_a_parallel_search() {
+  let template = BlockTemplate {
+    id: 69.into(),
+    previous_hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
+    timestamp: 1643220097,
+    data: "foo".to_string(),
+    difficulty: Difficulty::MEDIUM
+  };
+  let stop = AtomicBool::new(false);
+  let mut hard = Miner.start(BlockTemplate { difficulty: Difficulty(64), ..template.clone() });
+  let found = thread::scope(|scope| {
+    scope.spawn(|| { thread::sleep(Duration::from_millis(50)); stop.store(true, Ordering::Relaxed) });
+    hard.mine_parallel_until(3, &stop)
+  });
+  assert_eq!(found, None);
+  assert!(hard.next_nonce > 0);
+  assert_eq!(hard.next_nonce % CHUNK_SIZE, 0);
+
+  let path = std::env::temp_dir().join(format!("simple_blockchain_parallel_state_{}.json", std::process::id()));
+  let mut state = MiningState { next_nonce: 8 * CHUNK_SIZE, ..Miner.start(template.clone()) };
+  assert!(state.save(&path).is_ok());
+  let mut resumed = MiningState::<String>::load(&path).unwrap();
+  let _ = fs::remove_file(&path);
+  let block = resumed.mine_parallel_until(3, &AtomicBool::new(false)).unwrap();
+  assert_eq!(block, template.mine());
+  assert_eq!(resumed.next_nonce, 9386);
+
+  // Everything below next_nonce counts as searched, so a state past the solution finds a later one.
+  state.next_nonce = 10 * CHUNK_SIZE;
+  assert!(state.mine_parallel_until(3, &AtomicBool::new(false)).unwrap().nonce >= 10 * CHUNK_SIZE);
+}
@@ -0,0 +1,170 @@
+use std::collections::VecDeque;
+use crate::block::{ Block, BlockData };
+use crate::blockchain::{ Blockchain, verify_block_standalone };
+use crate::error::{ BlockchainError, BlockchainError::*, BlockValidationError::IdOutOfSequence };
+use crate::types::BlockHash;
+
+/// Blocks that arrived before their parents, waiting to be connected.
+///
+/// Holds at most `capacity` blocks; when full, the oldest is dropped to make room.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct OrphanPool<T = String> {
+  capacity: usize,
+  orphans: VecDeque<Block<T>>,
+}
+
+impl<T: BlockData> OrphanPool<T> {
+  /// Creates an empty pool that holds up to `capacity` blocks.
+  pub fn new(capacity: usize) -> Self {
+    Self { capacity, orphans: VecDeque::with_capacity(capacity) }
+  }
+
+  /// Returns the held blocks, oldest first.
+  pub fn orphans(&self) -> impl Iterator<Item = &Block<T>> {
+    self.orphans.iter()
+  }
+
+  /// Returns the parents to ask peers for: the previous hash of every held block whose parent isn't held too.
+  pub fn missing_parents(&self) -> Vec<&BlockHash> {
+    let mut missing = Vec::new();
+    for orphan in &self.orphans {
+      let parent_held = self.orphans.iter().any(|parent| parent.hash == orphan.previous_hash);
+      if !parent_held && !missing.contains(&&orphan.previous_hash) { missing.push(&orphan.previous_hash) };
+    }
+    missing
+  }
+
+  /// Returns the number of held blocks.
+  pub fn len(&self) -> usize {
+    self.orphans.len()
+  }
+
+  /// Returns `true` if no blocks are held.
+  pub fn is_empty(&self) -> bool {
+    self.orphans.is_empty()
+  }
+
+  fn hold(&mut self, block: Block<T>) {
+    if self.capacity == 0 || self.orphans.contains(&block) { return };
+    if self.orphans.len() == self.capacity { self.orphans.pop_front(); };
+    self.orphans.push_back(block);
+  }
+
+  fn take_child_of(&mut self, parent: &Block<T>) -> Option<Block<T>> {
+    let index = self.orphans.iter().position(|orphan| orphan.previous_hash == parent.hash)?;
+    self.orphans.remove(index)
+  }
+}
+
+impl<T: BlockData> Blockchain<Block<T>> {
+  /// Adds a block like [`Blockchain::add_block`], but holds it in `pool` if it's from further ahead than the next height,
+  /// and connects any held blocks that follow on once their parent is added.
+  /// Returns how many blocks were added. A block is only held once its size, proof of work and hash check out,
+  /// so junk can't push real orphans out of the pool; held blocks that turn out invalid are dropped.
+  ///
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::builder::ChainBuilder;
+  /// # use simple_blockchain::difficulty::Difficulty;
+  /// # use simple_blockchain::orphans::OrphanPool;
+  /// # use simple_blockchain::params::ChainParams;
+  /// let source = ChainBuilder::new(ChainParams { difficulty: Difficulty::DEMO, ..Default::default() })
+  ///   .block("one")
+  ///   .block("two")
+  ///   .block("three")
+  ///   .build();
+  /// let mut chain = source.clone();
+  /// chain.blocks.truncate(1);
+  /// let mut pool = OrphanPool::new(10);
+  ///
+  /// assert_eq!(chain.add_block_or_hold(source.blocks[3].clone(), &mut pool), Ok(0));
+  /// assert_eq!(chain.add_block_or_hold(source.blocks[2].clone(), &mut pool), Ok(0));
+  /// assert_eq!(pool.missing_parents(), [&source.blocks[1].hash]);
+  /// assert_eq!(chain.add_block_or_hold(source.blocks[1].clone(), &mut pool), Ok(3));
+  /// assert_eq!(chain.blocks, source.blocks);
+  /// assert!(pool.is_empty());
+  /// ```
+  ///
+  /// # Errors
+  /// Returns [`BlockchainError::InvalidChainLength`] if the blockchain is empty,
+  /// [`BlockchainError::InvalidBlock`] with the error from [`verify_block_standalone`] for a block from further ahead,
+  /// and the same errors as [`Blockchain::add_block`] for a block at the next height.
+  pub fn add_block_or_hold(&mut self, block: Block<T>, pool: &mut OrphanPool<T>) -> Result<usize, BlockchainError> {
+    let tail = self.blocks.last().ok_or(InvalidChainLength)?;
+    let next_id = tail.id.checked_add(1).ok_or(InvalidBlock(IdOutOfSequence))?;
+    if block.id > next_id {
+      verify_block_standalone(&block, &self.params).map_err(InvalidBlock)?;
+      pool.hold(block);
+      return Ok(0);
+    }
+
+    self.add_block(block)?;
+    let mut added = 1;
+    while let Some(orphan) = pool.take_child_of(self.blocks.last().unwrap()) {
+      if self.add_block(orphan).is_ok() { added += 1 };
+    }
+    Ok(added)
+  }
+}
+
+#[test]
+fn drops_the_oldest_orphan_when_full() {
+  let orphan = |id: u64| Block { id: id.into(), hash: format!("{}", id).into(), previous_hash: format!("{}", id - 1).into(), timestamp: 0, data: String::new(), nonce: 0 };
+  let mut pool = OrphanPool::new(2);
+  for id in [5, 7, 9] {
+    pool.hold(orphan(id));
+  }
+  pool.hold(orphan(9));
+  assert_eq!(pool.orphans().map(|block| block.id.0).collect::<Vec<_>>(), [7, 9]);
+  assert_eq!(pool.missing_parents(), [&BlockHash::from("6"), &BlockHash::from("8")]);
+}
+
+#[test]
+fn rejects_a_block_at_the_next_height_that_does_not_fit() {
+  use crate::difficulty::Difficulty;
+  use crate::error::BlockValidationError;
+  use crate::params::ChainParams;
+  let mut chain = Blockchain::with_params(ChainParams { difficulty: Difficulty::DEMO, ..Default::default() });
+  assert!(chain.genesis().is_ok());
+  let unlinked = Block::new(1, "elsewhere", "next".to_string());
+  assert_eq!(
+    chain.add_block_or_hold(unlinked, &mut OrphanPool::new(1)),
+    Err(InvalidBlock(BlockValidationError::PreviousHashMismatch))
+  );
+  assert_eq!(Blockchain::new().add_block_or_hold(chain.blocks[0].clone(), &mut OrphanPool::new(1)), Err(InvalidChainLength));
+}
+
+#[test]
+fn holds_only_blocks_that_verify_on_their_own() {
+  use crate::difficulty::Difficulty;
+  use crate::error::BlockValidationError;
+  use crate::params::ChainParams;
+  let mut chain = Blockchain::with_params(ChainParams { difficulty: Difficulty::DEMO, ..Default::default() });
+  assert!(chain.genesis().is_ok());
+  let mut pool = OrphanPool::new(1);
+  let junk = Block { id: 5.into(), hash: "ff".repeat(32).into(), previous_hash: "4".into(), timestamp: 0, data: String::new(), nonce: 0 };
+  assert_eq!(chain.add_block_or_hold(junk, &mut pool), Err(InvalidBlock(BlockValidationError::DifficultyNotMet)));
+  let forged = Block { data: "forged".to_string(), ..Block::new(5, "4", "real".to_string()) };
+  assert_eq!(chain.add_block_or_hold(forged, &mut pool), Err(InvalidBlock(BlockValidationError::HashMismatch)));
+  assert!(pool.is_empty());
+}
+
+#[test]
+fn holds_orphans_of_any_data() {
+  use crate::difficulty::Difficulty;
+  use crate::params::ChainParams;
+  use serde_json::{ json, Value };
+  let params: ChainParams<Value> = ChainParams { difficulty: Difficulty::DEMO, ..Default::default() };
+  let mut source = Blockchain::with_params(params);
+  assert!(source.genesis_with(json!({ "genesis": true })).is_ok());
+  for index in 1..3 {
+    let template = source.block_template(json!({ "index": index })).unwrap();
+    assert!(source.add_block(template.mine()).is_ok());
+  }
+  let mut chain = source.clone();
+  chain.blocks.truncate(1);
+  let mut pool = OrphanPool::new(1);
+  assert_eq!(chain.add_block_or_hold(source.blocks[2].clone(), &mut pool), Ok(0));
+  assert_eq!(chain.add_block_or_hold(source.blocks[1].clone(), &mut pool), Ok(2));
+  assert_eq!(chain.blocks, source.blocks);
+}
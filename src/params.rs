@@ -0,0 +1,198 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+use serde::{ Serialize, Serializer, Deserialize, Deserializer, de::Error };
+use crate::block::BlockData;
+use crate::difficulty::Difficulty;
+use crate::rules::{ Rule, default_rules };
+use crate::types::BlockHeight;
+
+/// The default [`ChainParams::max_block_size`], 1 MiB.
+pub const DEFAULT_MAX_BLOCK_SIZE: usize = 1024 * 1024;
+
+/// The rules a blockchain's blocks are validated against.
+///
+/// Rules are serialized by name, and left out when they're the default rules.
+/// Only the default rules can be deserialized, since custom rules like [`Validators`](crate::crypto::Validators)
+/// may hold state a name can't carry. Params naming any other rule fail to deserialize rather than load without it;
+/// add custom rules back with [`ChainParams::with_rule`] and serialize the params without them.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChainParams<T: BlockData = String> {
+  pub difficulty: Difficulty,
+  /// The largest a block may be, in bytes of [`Block::size`](crate::block::Block::size).
+  pub max_block_size: usize,
+  #[serde(default = "default_rules", skip_serializing_if = "has_default_rules")]
+  #[serde(serialize_with = "serialize_rule_names", deserialize_with = "deserialize_rule_names")]
+  pub rules: Vec<Arc<dyn Rule<T>>>,
+  /// Scheduled changes to these params. See [`ChainParams::at_height`].
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub upgrades: Vec<Upgrade>,
+}
+
+/// A coordinated change to the params, in force for blocks from `height` on.
+/// Fields left `None` keep their earlier value.
+///
+/// Rules can't be scheduled, since only their names are serialized,
+/// but a custom [`Rule`] can switch behaviour on the block's id.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Upgrade {
+  pub height: BlockHeight,
+  pub difficulty: Option<Difficulty>,
+  pub max_block_size: Option<usize>,
+}
+
+impl Upgrade {
+  /// An upgrade at `height` that changes nothing yet.
+  pub fn at(height: impl Into<BlockHeight>) -> Self {
+    Self { height: height.into(), difficulty: None, max_block_size: None }
+  }
+}
+
+impl<T: BlockData> ChainParams<T> {
+  /// Adds a rule, checked after the existing ones.
+  ///
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::params::ChainParams;
+  /// # use simple_blockchain::rules::Linkage;
+  /// let params: ChainParams = ChainParams::default().with_rule(Linkage);
+  /// assert_eq!(params.rules.last().unwrap().name(), "linkage");
+  /// ```
+  pub fn with_rule(mut self, rule: impl Rule<T> + 'static) -> Self {
+    self.rules.push(Arc::new(rule));
+    self
+  }
+
+  /// Removes every rule with the given name, e.g. to relax the timestamp rule on a private network.
+  ///
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::params::ChainParams;
+  /// let params: ChainParams = ChainParams::default().without_rule("timestamp");
+  /// assert!(params.rules.iter().all(|rule| rule.name() != "timestamp"));
+  /// ```
+  pub fn without_rule(mut self, name: &str) -> Self {
+    self.rules.retain(|rule| rule.name() != name);
+    self
+  }
+
+  /// Schedules an upgrade.
+  pub fn with_upgrade(mut self, upgrade: Upgrade) -> Self {
+    self.upgrades.push(upgrade);
+    self
+  }
+
+  /// Returns the params in force for the block at `height`:
+  /// these params with every upgrade at or below `height` applied in order of height.
+  /// Borrows these params, without copying the rules, until an upgrade applies.
+  ///
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::difficulty::Difficulty;
+  /// # use simple_blockchain::params::{ ChainParams, DEFAULT_MAX_BLOCK_SIZE, Upgrade };
+  /// # use simple_blockchain::types::BlockHeight;
+  /// let params: ChainParams = ChainParams { difficulty: Difficulty::DEMO, ..Default::default() }
+  ///   .with_upgrade(Upgrade { difficulty: Some(Difficulty::EASY), ..Upgrade::at(100) })
+  ///   .with_upgrade(Upgrade { max_block_size: Some(2 * DEFAULT_MAX_BLOCK_SIZE), ..Upgrade::at(200) });
+  ///
+  /// assert_eq!(params.at_height(BlockHeight(99)).difficulty, Difficulty::DEMO);
+  /// assert_eq!(params.at_height(BlockHeight(100)).difficulty, Difficulty::EASY);
+  /// assert_eq!(params.at_height(BlockHeight(100)).max_block_size, DEFAULT_MAX_BLOCK_SIZE);
+  /// assert_eq!(params.at_height(BlockHeight(250)).max_block_size, 2 * DEFAULT_MAX_BLOCK_SIZE);
+  /// ```
+  pub fn at_height(&self, height: BlockHeight) -> Cow<'_, Self> {
+    if self.upgrades.iter().all(|upgrade| upgrade.height.0 > height.0) { return Cow::Borrowed(self) };
+    let mut upgrades = self.upgrades.iter().filter(|upgrade| upgrade.height.0 <= height.0).collect::<Vec<_>>();
+    upgrades.sort_by_key(|upgrade| upgrade.height.0);
+    let mut params = self.clone();
+    for upgrade in upgrades {
+      params.difficulty = upgrade.difficulty.unwrap_or(params.difficulty);
+      params.max_block_size = upgrade.max_block_size.unwrap_or(params.max_block_size);
+    }
+    Cow::Owned(params)
+  }
+}
+
+impl<T: BlockData> Default for ChainParams<T> {
+  fn default() -> Self {
+    Self {
+      difficulty: Difficulty::default(),
+      max_block_size: DEFAULT_MAX_BLOCK_SIZE,
+      rules: default_rules(),
+      upgrades: vec![]
+    }
+  }
+}
+
+fn has_default_rules<T: BlockData>(rules: &[Arc<dyn Rule<T>>]) -> bool {
+  rules.iter().map(|rule| rule.name()).eq(default_rules::<T>().iter().map(|rule| rule.name()))
+}
+
+fn serialize_rule_names<T: BlockData, S: Serializer>(rules: &[Arc<dyn Rule<T>>], serializer: S) -> Result<S::Ok, S::Error> {
+  serializer.collect_seq(rules.iter().map(|rule| rule.name()))
+}
+
+fn deserialize_rule_names<'de, T: BlockData, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Arc<dyn Rule<T>>>, D::Error> {
+  let defaults = default_rules::<T>();
+  Vec::<String>::deserialize(deserializer)?.iter()
+    .map(|name| {
+      defaults.iter().find(|rule| rule.name() == name).cloned()
+        .ok_or_else(|| D::Error::custom(format!("unknown rule `{}`; only default rules can be deserialized", name)))
+    })
+    .collect()
+}
+
+// Rules are compared by name, since trait objects can't be compared directly.
+impl<T: BlockData> PartialEq for ChainParams<T> {
+  fn eq(&self, other: &Self) -> bool {
+    self.difficulty == other.difficulty
+      && self.max_block_size == other.max_block_size
+      && self.rules.iter().map(|rule| rule.name()).eq(other.rules.iter().map(|rule| rule.name()))
+      && self.upgrades == other.upgrades
+  }
+}
+
+#[test]
+fn serializes_rules_by_name() {
+  let params: ChainParams = ChainParams { difficulty: Difficulty::DEMO, ..Default::default() };
+  let json = serde_json::to_string(&params).unwrap();
+  assert_eq!(json, r#"{"difficulty":8,"max_block_size":1048576}"#);
+  assert_eq!(serde_json::from_str::<ChainParams>(&json).unwrap(), params);
+
+  let relaxed = params.without_rule("timestamp");
+  let json = serde_json::to_string(&relaxed).unwrap();
+  assert_eq!(json, r#"{"difficulty":8,"max_block_size":1048576,"rules":["id-sequence","linkage","difficulty","size","hash-match"]}"#);
+  assert_eq!(serde_json::from_str::<ChainParams>(&json).unwrap(), relaxed);
+}
+
+#[test]
+fn refuses_to_drop_custom_rules() {
+  use crate::crypto::{ Keypair, Signed, Validators };
+  let params: ChainParams<Signed<String>> = ChainParams::default().with_rule(Validators::new([Keypair::from_seed([1; 32]).public_key()]));
+  let json = serde_json::to_string(&params).unwrap();
+  let error = serde_json::from_str::<ChainParams<Signed<String>>>(&json).unwrap_err();
+  assert!(error.to_string().contains("unknown rule `validator-signature`"), "{}", error);
+}
+
+#[test]
+fn compares_rules_by_name() {
+  assert_eq!(ChainParams::<String>::default(), ChainParams::default());
+  assert_ne!(ChainParams::<String>::default(), ChainParams::default().without_rule("size"));
+}
+
+#[test]
+fn serializes_upgrades() {
+  let params = ChainParams::default().with_upgrade(Upgrade { difficulty: Some(Difficulty::EASY), ..Upgrade::at(10) });
+  let json = serde_json::to_string(&params).unwrap();
+  assert_eq!(
+    json,
+    r#"{"difficulty":16,"max_block_size":1048576,"upgrades":[{"height":10,"difficulty":12,"max_block_size":null}]}"#
+  );
+  assert_eq!(serde_json::from_str::<ChainParams>(&json).unwrap(), params);
+}
+
+#[test]
+fn borrows_params_until_an_upgrade_applies() {
+  let params: ChainParams = ChainParams::default().with_upgrade(Upgrade { difficulty: Some(Difficulty::EASY), ..Upgrade::at(10) });
+  assert!(matches!(params.at_height(BlockHeight(9)), Cow::Borrowed(_)));
+  assert!(matches!(params.at_height(BlockHeight(10)), Cow::Owned(_)));
+}
@@ -0,0 +1,99 @@
+use std::collections::BTreeMap;
+use crate::block::Block;
+use crate::blockchain::Blockchain;
+
+/// Independent blockchains hosted side by side, each under its own chain id and with its own parameters.
+///
+/// # Examples
+/// ```
+/// # use simple_blockchain::blockchain::Blockchain;
+/// # use simple_blockchain::difficulty::Difficulty;
+/// # use simple_blockchain::params::ChainParams;
+/// # use simple_blockchain::registry::ChainRegistry;
+/// let mut registry = ChainRegistry::new();
+/// registry.insert("group-a", Blockchain::new());
+/// registry.insert("group-b", Blockchain::with_params(ChainParams { difficulty: Difficulty::DEMO, ..Default::default() }));
+///
+/// registry.get_mut("group-b").unwrap().genesis();
+/// assert!(registry.get("group-a").unwrap().blocks.is_empty());
+/// assert_eq!(registry.get("group-b").unwrap().blocks.len(), 1);
+/// assert_eq!(registry.chain_ids().collect::<Vec<_>>(), ["group-a", "group-b"]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ChainRegistry {
+  chains: BTreeMap<String, Blockchain<Block>>,
+}
+
+impl ChainRegistry {
+  /// Creates an empty registry.
+  pub fn new() -> Self {
+    Self { chains: BTreeMap::new() }
+  }
+
+  /// Hosts `chain` under `chain_id`, returning the chain it replaces, if any.
+  pub fn insert(&mut self, chain_id: impl Into<String>, chain: Blockchain<Block>) -> Option<Blockchain<Block>> {
+    self.chains.insert(chain_id.into(), chain)
+  }
+
+  /// Stops hosting the chain under `chain_id` and returns it.
+  pub fn remove(&mut self, chain_id: &str) -> Option<Blockchain<Block>> {
+    self.chains.remove(chain_id)
+  }
+
+  /// Returns the chain hosted under `chain_id`.
+  pub fn get(&self, chain_id: &str) -> Option<&Blockchain<Block>> {
+    self.chains.get(chain_id)
+  }
+
+  /// Returns the chain hosted under `chain_id`, for adding blocks.
+  pub fn get_mut(&mut self, chain_id: &str) -> Option<&mut Blockchain<Block>> {
+    self.chains.get_mut(chain_id)
+  }
+
+  /// Returns the hosted chain ids in order.
+  pub fn chain_ids(&self) -> impl Iterator<Item = &str> {
+    self.chains.keys().map(|chain_id| chain_id.as_str())
+  }
+
+  /// Returns the hosted chains with their ids, in order of id.
+  pub fn iter(&self) -> impl Iterator<Item = (&str, &Blockchain<Block>)> {
+    self.chains.iter().map(|(chain_id, chain)| (chain_id.as_str(), chain))
+  }
+
+  /// Returns the number of hosted chains.
+  pub fn len(&self) -> usize {
+    self.chains.len()
+  }
+
+  /// Returns `true` if no chains are hosted.
+  pub fn is_empty(&self) -> bool {
+    self.chains.is_empty()
+  }
+}
+
+#[test]
+fn keeps_chains_independent() {
+  use crate::difficulty::Difficulty;
+  use crate::params::ChainParams;
+  let mut registry = ChainRegistry::new();
+  registry.insert("demo", Blockchain::with_params(ChainParams { difficulty: Difficulty::DEMO, ..Default::default() }));
+  registry.insert("strict", Blockchain::with_params(ChainParams { difficulty: Difficulty::DEMO, max_block_size: 16, ..Default::default() }));
+  assert!(registry.get_mut("demo").unwrap().genesis().is_ok());
+  assert!(registry.get_mut("strict").unwrap().genesis().is_ok());
+
+  let demo_genesis = registry.get("demo").unwrap().blocks[0].clone();
+  let block = Block::new(demo_genesis.id + 1, &demo_genesis.hash, "next".to_string());
+  assert!(registry.get_mut("demo").unwrap().add_block(block.clone()).is_ok());
+  assert!(registry.get_mut("strict").unwrap().add_block(block).is_err());
+  assert_eq!(registry.len(), 2);
+}
+
+#[test]
+fn replaces_and_removes_chains() {
+  let mut registry = ChainRegistry::new();
+  assert!(registry.is_empty());
+  assert_eq!(registry.insert("a", Blockchain::new()), None);
+  assert_eq!(registry.insert("a", Blockchain::new()), Some(Blockchain::new()));
+  assert_eq!(registry.remove("a"), Some(Blockchain::new()));
+  assert_eq!(registry.get("a"), None);
+}
@@ -0,0 +1,201 @@
+use serde::{ Serialize, Deserialize };
+use crate::block::Block;
+use crate::blockchain::{ Blockchain, verify_pow };
+use crate::decode::{ self, DecodeLimits };
+use crate::error::{ BlockchainError, BlockchainError::*, BlockValidationError };
+use crate::registry::ChainRegistry;
+use crate::types::BlockHeight;
+
+/// A copy of a block's data from another chain, stored as the data of a block on the destination chain.
+///
+/// The whole source block travels with the copy as its inclusion proof:
+/// anyone can recompute its hash and check its proof of work,
+/// and anyone with the source chain can check the block is on it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RelayedEntry {
+  pub source_chain: String,
+  pub source_block: Block,
+}
+
+impl RelayedEntry {
+  /// Reads an entry back out of a destination block's data. Returns `None` if the data isn't an entry.
+  pub fn from_data(data: &str) -> Option<Self> {
+    decode::from_json(data, DecodeLimits::default()).ok()
+  }
+
+  /// Serializes the entry for use as a block's data.
+  pub fn to_data(&self) -> String {
+    serde_json::to_string(self).unwrap()
+  }
+
+  /// Returns `true` if the source block is self-consistent under the source chain's parameters
+  /// and is the block at its height on `source`.
+  pub fn verify(&self, source: &Blockchain<Block>) -> bool {
+    let block = &self.source_block;
+    verify_pow(block, source.params.at_height(BlockHeight(block.id.0)).difficulty)
+      && source.block_at(BlockHeight(block.id.0)) == Some(block)
+  }
+}
+
+/// What one [`Relay::run`] did.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RelayReport {
+  /// How many entries were added to the destination chain.
+  pub relayed: usize,
+  /// The source heights of entries the destination chain rejected, e.g. for being over its maximum block size,
+  /// with the reason. They're passed over for good.
+  pub skipped: Vec<(BlockHeight, BlockValidationError)>,
+}
+
+/// Copies blocks whose data contains a pattern from one chain in a [`ChainRegistry`] into another.
+///
+/// Blocks that are themselves relayed entries are never relayed again, so a relay from a chain into itself,
+/// or two relays in opposite directions, copy each original block once rather than forever.
+///
+/// # Examples
+/// ```
+/// # use simple_blockchain::blockchain::Blockchain;
+/// # use simple_blockchain::difficulty::Difficulty;
+/// # use simple_blockchain::params::ChainParams;
+/// # use simple_blockchain::registry::ChainRegistry;
+/// # use simple_blockchain::relay::{ Relay, RelayedEntry };
+/// let params = ChainParams { difficulty: Difficulty::DEMO, ..Default::default() };
+/// let mut registry = ChainRegistry::new();
+/// registry.insert("a", Blockchain::with_params(params.clone()));
+/// registry.insert("b", Blockchain::with_params(params));
+/// for chain_id in ["a", "b"] {
+///   registry.get_mut(chain_id).unwrap().genesis();
+/// }
+/// let a = registry.get_mut("a").unwrap();
+/// let block = a.block_template("transfer: 5".to_string()).unwrap().mine();
+/// a.add_block(block).unwrap();
+///
+/// let mut relay = Relay::new("a", "b", "transfer");
+/// assert_eq!(relay.run(&mut registry).map(|report| report.relayed), Ok(1));
+/// assert_eq!(relay.run(&mut registry).map(|report| report.relayed), Ok(0));
+///
+/// let relayed = &registry.get("b").unwrap().blocks[1];
+/// let entry = RelayedEntry::from_data(&relayed.data).unwrap();
+/// assert_eq!(entry.source_block.data, "transfer: 5");
+/// assert!(entry.verify(registry.get("a").unwrap()));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Relay {
+  pub source: String,
+  pub destination: String,
+  pub pattern: String,
+  /// The first source height the next run looks at.
+  pub next_height: BlockHeight,
+}
+
+impl Relay {
+  /// Creates a relay that starts watching `source` from its genesis block.
+  pub fn new(source: impl Into<String>, destination: impl Into<String>, pattern: impl Into<String>) -> Self {
+    Self { source: source.into(), destination: destination.into(), pattern: pattern.into(), next_height: BlockHeight(0) }
+  }
+
+  /// Relays every matching source block added since the last run, mining one destination block per entry.
+  /// Source blocks holding a [`RelayedEntry`] are skipped, and so are entries the destination chain rejects,
+  /// which are listed in the report.
+  ///
+  /// # Errors
+  /// Returns [`BlockchainError::UnknownChain`] if either chain isn't in the registry,
+  /// or the error from building the destination's next block, e.g. [`BlockchainError::InvalidChainLength`]
+  /// if it has no genesis block. Entries handled before an error stay handled.
+  pub fn run(&mut self, registry: &mut ChainRegistry) -> Result<RelayReport, BlockchainError> {
+    if registry.get(&self.destination).is_none() { return Err(UnknownChain(self.destination.clone())) };
+    let source = registry.get(&self.source).ok_or_else(|| UnknownChain(self.source.clone()))?;
+    let entries = source.blocks.iter()
+      .skip(self.next_height.0 as usize)
+      .filter(|block| block.data.contains(&self.pattern) && RelayedEntry::from_data(&block.data).is_none())
+      .map(|block| RelayedEntry { source_chain: self.source.clone(), source_block: block.clone() })
+      .collect::<Vec<_>>();
+    let source_length = source.blocks.len() as u64;
+
+    let destination = registry.get_mut(&self.destination).ok_or_else(|| UnknownChain(self.destination.clone()))?;
+    let mut report = RelayReport::default();
+    for entry in &entries {
+      let template = destination.block_template(entry.to_data())?;
+      match destination.add_block(template.mine()) {
+        Ok(()) => report.relayed += 1,
+        Err(InvalidBlock(error)) => report.skipped.push((BlockHeight(entry.source_block.id.0), error)),
+        Err(error) => return Err(error),
+      }
+      self.next_height = BlockHeight(entry.source_block.id.0.saturating_add(1));
+    }
+    self.next_height = BlockHeight(source_length.max(self.next_height.0));
+    Ok(report)
+  }
+}
+
+#[test]
+fn reports_unknown_chains() {
+  let mut registry = ChainRegistry::new();
+  registry.insert("a", Blockchain::new());
+  assert_eq!(Relay::new("a", "nowhere", "x").run(&mut registry), Err(UnknownChain("nowhere".to_string())));
+  assert_eq!(Relay::new("nowhere", "a", "x").run(&mut registry), Err(UnknownChain("nowhere".to_string())));
+}
+
+#[test]
+fn never_relays_a_relayed_entry() {
+  use crate::difficulty::Difficulty;
+  use crate::params::ChainParams;
+  let mut registry = ChainRegistry::new();
+  registry.insert("a", Blockchain::with_params(ChainParams { difficulty: Difficulty::DEMO, ..Default::default() }));
+  let a = registry.get_mut("a").unwrap();
+  assert!(a.genesis().is_ok());
+  let block = a.block_template("transfer: 5".to_string()).unwrap().mine();
+  assert!(a.add_block(block).is_ok());
+
+  let mut relay = Relay::new("a", "a", "transfer");
+  assert_eq!(relay.run(&mut registry).map(|report| report.relayed), Ok(1));
+  assert_eq!(relay.run(&mut registry).map(|report| report.relayed), Ok(0));
+  assert_eq!(registry.get("a").unwrap().blocks.len(), 3);
+}
+
+#[test]
+fn skips_entries_the_destination_rejects() {
+  use crate::difficulty::Difficulty;
+  use crate::error::BlockValidationError::TooLarge;
+  use crate::params::ChainParams;
+  let params = ChainParams { difficulty: Difficulty::DEMO, ..Default::default() };
+  let mut registry = ChainRegistry::new();
+  registry.insert("a", Blockchain::with_params(params.clone()));
+  registry.insert("b", Blockchain::with_params(ChainParams { max_block_size: 1024, ..params }));
+  for chain_id in ["a", "b"] {
+    assert!(registry.get_mut(chain_id).unwrap().genesis().is_ok());
+  }
+  let a = registry.get_mut("a").unwrap();
+  for data in [format!("transfer: {}", "5".repeat(2048)), "transfer: 6".to_string()] {
+    let block = a.block_template(data).unwrap().mine();
+    assert!(a.add_block(block).is_ok());
+  }
+
+  let mut relay = Relay::new("a", "b", "transfer");
+  assert_eq!(relay.run(&mut registry), Ok(RelayReport { relayed: 1, skipped: vec![(BlockHeight(1), TooLarge)] }));
+  assert_eq!(relay.next_height, BlockHeight(3));
+  assert_eq!(relay.run(&mut registry), Ok(RelayReport::default()));
+  let relayed = RelayedEntry::from_data(&registry.get("b").unwrap().blocks[1].data).unwrap();
+  assert_eq!(relayed.source_block.data, "transfer: 6");
+}
+
+#[test]
+fn rejects_entries_not_on_the_source_chain() {
+  use crate::builder::ChainBuilder;
+  use crate::difficulty::Difficulty;
+  use crate::params::ChainParams;
+  let chains = ChainBuilder::new(ChainParams { difficulty: Difficulty::DEMO, ..Default::default() })
+    .block("transfer: 5")
+    .fork_at(0)
+    .block("transfer: 6")
+    .build_all();
+  let entry = RelayedEntry { source_chain: "a".to_string(), source_block: chains[1].blocks[1].clone() };
+  assert!(entry.verify(&chains[1]));
+  assert!(!entry.verify(&chains[0]));
+
+  let forged = RelayedEntry {
+    source_block: Block { data: "transfer: 500".to_string(), ..chains[1].blocks[1].clone() },
+    ..entry
+  };
+  assert!(!forged.verify(&chains[1]));
+}
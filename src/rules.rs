@@ -0,0 +1,299 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+use chrono::Utc;
+use crate::block::{ Block, BlockData };
+use crate::error::{ BlockValidationError, BlockValidationError::* };
+use crate::helpers::calculate_hash;
+use crate::params::ChainParams;
+
+/// How far ahead of the local clock a block's timestamp may be, in seconds.
+pub const MAX_FUTURE_DRIFT: i64 = 2 * 60 * 60;
+
+/// One check a block must pass to be added after `previous_block`.
+///
+/// A blockchain runs the rules in its [`ChainParams::rules`] in order and stops at the first failure.
+/// Applications can add their own rules, or remove default ones by name.
+/// Rules are generic over the blocks' data; the built-in ones apply to any [`BlockData`].
+///
+/// # Examples
+/// ```
+/// # use simple_blockchain::block::Block;
+/// # use simple_blockchain::blockchain::Blockchain;
+/// # use simple_blockchain::error::{ BlockchainError, BlockValidationError };
+/// # use simple_blockchain::params::ChainParams;
+/// # use simple_blockchain::rules::Rule;
+/// #[derive(Debug)]
+/// struct NoShouting;
+///
+/// impl Rule for NoShouting {
+///   fn name(&self) -> &str { "no-shouting" }
+///
+///   fn check(&self, block: &Block, _: &Block, _: &ChainParams) -> Result<(), BlockValidationError> {
+///     if block.data.chars().any(|c| c.is_lowercase()) { return Ok(()) };
+///     Err(BlockValidationError::Custom { rule: self.name().to_string(), reason: "all caps".to_string() })
+///   }
+/// }
+///
+/// let mut my_blockchain = Blockchain::with_params(ChainParams::default().with_rule(NoShouting));
+/// my_blockchain.genesis();
+/// let block = my_blockchain.block_template("HELLO".to_string()).unwrap().mine();
+/// assert!(matches!(
+///   my_blockchain.add_block(block),
+///   Err(BlockchainError::InvalidBlock(BlockValidationError::Custom { .. }))
+/// ));
+/// ```
+pub trait Rule<T: BlockData = String>: Debug + Send + Sync {
+  /// A short, unique, kebab-case name for the rule.
+  fn name(&self) -> &str;
+
+  fn check(&self, block: &Block<T>, previous_block: &Block<T>, params: &ChainParams<T>) -> Result<(), BlockValidationError>;
+
+  /// Checks the genesis block, which has no previous block to run [`Rule::check`] against.
+  /// Most rules have nothing to check there; the default accepts any genesis block.
+  fn check_genesis(&self, _genesis: &Block<T>, _params: &ChainParams<T>) -> Result<(), BlockValidationError> {
+    Ok(())
+  }
+}
+
+/// The block's id is one more than the previous block's.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct IdSequence;
+
+/// The block's previous hash is the previous block's hash.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Linkage;
+
+/// The block is no bigger than [`ChainParams::max_block_size`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BlockSize;
+
+/// The block's timestamp isn't before the previous block's, or more than [`MAX_FUTURE_DRIFT`] ahead of now.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Timestamp;
+
+/// The block's claimed hash meets [`ChainParams::difficulty`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ProofOfWork;
+
+/// The block's hash is the hash of its contents.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct HashMatch;
+
+impl<T: BlockData> Rule<T> for IdSequence {
+  fn name(&self) -> &str {
+    "id-sequence"
+  }
+
+  fn check(&self, block: &Block<T>, previous_block: &Block<T>, _: &ChainParams<T>) -> Result<(), BlockValidationError> {
+    if previous_block.id.checked_add(1) != Some(block.id) { return Err(IdOutOfSequence) };
+    Ok(())
+  }
+}
+
+impl<T: BlockData> Rule<T> for Linkage {
+  fn name(&self) -> &str {
+    "linkage"
+  }
+
+  fn check(&self, block: &Block<T>, previous_block: &Block<T>, _: &ChainParams<T>) -> Result<(), BlockValidationError> {
+    if block.previous_hash != previous_block.hash { return Err(PreviousHashMismatch) };
+    Ok(())
+  }
+}
+
+impl<T: BlockData> Rule<T> for BlockSize {
+  fn name(&self) -> &str {
+    "size"
+  }
+
+  fn check(&self, block: &Block<T>, _: &Block<T>, params: &ChainParams<T>) -> Result<(), BlockValidationError> {
+    if block.size() > params.max_block_size { return Err(TooLarge) };
+    Ok(())
+  }
+}
+
+impl<T: BlockData> Rule<T> for Timestamp {
+  fn name(&self) -> &str {
+    "timestamp"
+  }
+
+  fn check(&self, block: &Block<T>, previous_block: &Block<T>, _: &ChainParams<T>) -> Result<(), BlockValidationError> {
+    if block.timestamp < previous_block.timestamp
+    || block.timestamp > Utc::now().timestamp() + MAX_FUTURE_DRIFT {
+      return Err(InvalidTimestamp);
+    }
+    Ok(())
+  }
+}
+
+impl<T: BlockData> Rule<T> for ProofOfWork {
+  fn name(&self) -> &str {
+    "difficulty"
+  }
+
+  fn check(&self, block: &Block<T>, _: &Block<T>, params: &ChainParams<T>) -> Result<(), BlockValidationError> {
+    if block.hash.len() != 64 || !params.difficulty.is_met_by(&block.hash) { return Err(DifficultyNotMet) };
+    Ok(())
+  }
+}
+
+impl<T: BlockData> Rule<T> for HashMatch {
+  fn name(&self) -> &str {
+    "hash-match"
+  }
+
+  fn check(&self, block: &Block<T>, _: &Block<T>, _: &ChainParams<T>) -> Result<(), BlockValidationError> {
+    let hash = calculate_hash(block.id.0, block.timestamp, &block.previous_hash, &block.data.canonical(), block.nonce);
+    if block.hash != hash { return Err(HashMismatch) };
+    Ok(())
+  }
+}
+
+/// The built-in rules, cheapest first so junk is rejected before the hash is recomputed.
+/// [`ProofOfWork`] only reads the claimed hash, so it runs before [`BlockSize`] and [`HashMatch`], which serialize the data.
+pub fn default_rules<T: BlockData>() -> Vec<Arc<dyn Rule<T>>> {
+  vec![
+    Arc::new(IdSequence),
+    Arc::new(Linkage),
+    Arc::new(ProofOfWork),
+    Arc::new(Timestamp),
+    Arc::new(BlockSize),
+    Arc::new(HashMatch),
+  ]
+}
+
+#[test]
+fn fixture_passes_every_default_rule() {
+  use crate::types::BlockId;
+  let block = Block {
+    id: BlockId(1),
+    hash: "00005ea81511a2a24a25a2055d5fc581879b8cfbedc5ddfb6918caed4917138e".into(),
+    previous_hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
+    timestamp: 1643223669,
+    data: String::from("next"),
+    nonce: 24271,
+  };
+  let previous_block = Block {
+    id: BlockId(0),
+    hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
+    previous_hash: "genesis".into(),
+    timestamp: 1643223669,
+    data: String::from("genesis!"),
+    nonce: 2836,
+  };
+  let params = ChainParams::default();
+  assert!(default_rules().iter().all(|rule| rule.check(&block, &previous_block, &params).is_ok()));
+}
+
+#[test]
+fn each_rule_reports_its_own_error() {
+  use crate::types::BlockId;
+  let block = Block {
+    id: BlockId(1),
+    hash: "00005ea81511a2a24a25a2055d5fc581879b8cfbedc5ddfb6918caed4917138e".into(),
+    previous_hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
+    timestamp: 1643223669,
+    data: String::from("next"),
+    nonce: 24271,
+  };
+  let previous_block = Block {
+    id: BlockId(0),
+    hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
+    previous_hash: "genesis".into(),
+    timestamp: 1643223669,
+    data: String::from("genesis!"),
+    nonce: 2836,
+  };
+  let params = ChainParams::default();
+  let cases: Vec<(Box<dyn Rule>, Block, BlockValidationError)> = vec![
+    (Box::new(IdSequence), Block { id: previous_block.id + 2, ..block.clone() }, IdOutOfSequence),
+    (Box::new(Linkage), Block { previous_hash: "elsewhere".into(), ..block.clone() }, PreviousHashMismatch),
+    (Box::new(BlockSize), Block { data: "x".repeat(params.max_block_size), ..block.clone() }, TooLarge),
+    (Box::new(Timestamp), Block { timestamp: previous_block.timestamp - 1, ..block.clone() }, InvalidTimestamp),
+    (Box::new(Timestamp), Block { timestamp: Utc::now().timestamp() + MAX_FUTURE_DRIFT + 60, ..block.clone() }, InvalidTimestamp),
+    (Box::new(ProofOfWork), Block { hash: "ff".repeat(32).into(), ..block.clone() }, DifficultyNotMet),
+    (Box::new(HashMatch), Block { nonce: 1, ..block.clone() }, HashMismatch),
+  ];
+  for (rule, invalid_block, expected) in cases {
+    assert_eq!(rule.check(&invalid_block, &previous_block, &params), Err(expected), "{}", rule.name());
+  }
+}
+
+#[test]
+fn checks_the_work_before_the_size() {
+  use crate::types::BlockId;
+  let previous_block = Block {
+    id: BlockId(0),
+    hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
+    previous_hash: "genesis".into(),
+    timestamp: 1643223669,
+    data: String::from("genesis!"),
+    nonce: 2836,
+  };
+  let spam = Block {
+    id: BlockId(1),
+    hash: "ff".repeat(32).into(),
+    previous_hash: previous_block.hash.clone(),
+    timestamp: previous_block.timestamp,
+    data: "x".repeat(2 * crate::params::DEFAULT_MAX_BLOCK_SIZE),
+    nonce: 0,
+  };
+  let params = ChainParams::default();
+  assert_eq!(params.rules.iter().try_for_each(|rule| rule.check(&spam, &previous_block, &params)), Err(DifficultyNotMet));
+}
+
+#[test]
+fn rule_names_are_unique() {
+  let rules = default_rules::<String>();
+  let mut names = rules.iter().map(|rule| rule.name()).collect::<Vec<_>>();
+  names.sort();
+  names.dedup();
+  assert_eq!(names.len(), rules.len());
+}
+
+#[test]
+fn timestamp_rule_holds_under_adversarial_patterns() {
+  use crate::builder::ChainBuilder;
+  use crate::difficulty::{ Difficulty, estimated_time_to_mine };
+  // Every pattern is mined onto the same genesis block, so the timestamps don't depend on how long mining takes.
+  let genesis = ChainBuilder::new(ChainParams { difficulty: Difficulty(0), ..Default::default() });
+  let start = genesis.clone().build().blocks[0].timestamp;
+  // Mines a chain whose blocks after genesis have these timestamps, and reports which ones the chain accepts.
+  let accepted = |timestamps: &[i64]| {
+    let chain = timestamps.iter()
+      .fold(genesis.clone(), |builder, &timestamp| builder.block_at("next", timestamp))
+      .build();
+    let verdicts = chain.blocks.windows(2).map(|pair| chain.validate_block(&pair[1], &pair[0]).is_ok()).collect::<Vec<_>>();
+    assert_eq!(chain.is_chain_valid(), verdicts.iter().all(|&ok| ok));
+    verdicts
+  };
+
+  let zero_intervals = [start; 5];
+  assert_eq!(accepted(&zero_intervals), [true; 5]);
+
+  let sawtooth = (0..8).map(|step| start + if step % 2 == 0 { 10 * step } else { 10 * step - 15 }).collect::<Vec<_>>();
+  assert_eq!(accepted(&sawtooth), [true, false, true, false, true, false, true, false]);
+
+  // The genesis block is no later than now, and the test would have to run for ten minutes to move the cutoff.
+  let far_future = [start, start + MAX_FUTURE_DRIFT - 600, start + MAX_FUTURE_DRIFT + 600, i64::MAX];
+  assert_eq!(accepted(&far_future), [true, true, false, false]);
+
+  // Difficulties picked from the same intervals stay in bounds, and their estimated block times land within
+  // a factor of the square root of two of the interval, since the difficulty rounds to the nearest power of two.
+  let hashrate = 1000.0;
+  for pattern in [&zero_intervals[..], &sawtooth, &far_future] {
+    for pair in pattern.windows(2) {
+      let seconds = pair[1].saturating_sub(pair[0]) as f64;
+      let difficulty = Difficulty::from_target_block_time(hashrate, seconds);
+      assert!(difficulty <= Difficulty::MAX, "{} s", seconds);
+      let estimate = estimated_time_to_mine(&difficulty, hashrate).as_secs_f64();
+      if hashrate * seconds <= 1.0 {
+        assert_eq!(difficulty, Difficulty(0), "{} s", seconds);
+        assert_eq!(estimate, 1.0 / hashrate);
+      } else {
+        let ratio = estimate / seconds;
+        assert!(ratio <= 1.0001 * std::f64::consts::SQRT_2 && ratio * 1.0001 * std::f64::consts::SQRT_2 >= 1.0, "{} s estimated at {} s", seconds, estimate);
+      }
+    }
+  }
+}
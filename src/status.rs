@@ -0,0 +1,121 @@
+use serde::Serialize;
+use crate::error::{ BlockchainError, BlockValidationError };
+
+/// How an error is reported across an HTTP, gRPC or C boundary.
+///
+/// `code` and `kind` are stable, so clients can branch on them.
+/// The C bindings return `code` directly.
+///
+/// # Examples
+/// ```
+/// # use simple_blockchain::error::{ BlockchainError, BlockValidationError };
+/// # use simple_blockchain::status::ErrorStatus;
+/// let status = ErrorStatus::from(&BlockchainError::InvalidBlock(BlockValidationError::PreviousHashMismatch));
+/// assert_eq!((status.code, status.http_status, status.grpc_code), (11, 422, 3));
+/// assert_eq!(
+///   status.to_json(),
+///   r#"{"code":11,"kind":"previous_hash_mismatch","message":"invalid block: previous hash doesn't match the previous block's hash"}"#
+/// );
+/// ```
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ErrorStatus {
+  pub code: i32,
+  /// The error kind in snake case.
+  pub kind: &'static str,
+  pub message: String,
+  /// The failing rule's name, for custom rule failures.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub rule: Option<String>,
+  #[serde(skip)]
+  pub http_status: u16,
+  #[serde(skip)]
+  pub grpc_code: i32,
+}
+
+// gRPC status codes, from google.rpc.Code.
+const GRPC_INVALID_ARGUMENT: i32 = 3;
+const GRPC_NOT_FOUND: i32 = 5;
+const GRPC_RESOURCE_EXHAUSTED: i32 = 8;
+const GRPC_FAILED_PRECONDITION: i32 = 9;
+const GRPC_INTERNAL: i32 = 13;
+const GRPC_DATA_LOSS: i32 = 15;
+
+impl ErrorStatus {
+  /// Returns the machine-readable JSON body for the error: its code, kind, message and, for custom rules, rule.
+  pub fn to_json(&self) -> String {
+    serde_json::to_string(self).unwrap()
+  }
+
+  fn new(code: i32, kind: &'static str, http_status: u16, grpc_code: i32, message: String) -> Self {
+    Self { code, kind, message, rule: None, http_status, grpc_code }
+  }
+}
+
+impl From<&BlockchainError> for ErrorStatus {
+  fn from(error: &BlockchainError) -> Self {
+    let message = error.to_string();
+    match error {
+      BlockchainError::InvalidChainLength => Self::new(1, "invalid_chain_length", 409, GRPC_FAILED_PRECONDITION, message),
+      BlockchainError::MalformedJson(_) => Self::new(2, "malformed_json", 400, GRPC_INVALID_ARGUMENT, message),
+      BlockchainError::ChecksumMismatch => Self::new(3, "checksum_mismatch", 422, GRPC_DATA_LOSS, message),
+      BlockchainError::Io(_) => Self::new(4, "io", 500, GRPC_INTERNAL, message),
+      BlockchainError::UnknownChain(_) => Self::new(5, "unknown_chain", 404, GRPC_NOT_FOUND, message),
+      BlockchainError::InputTooLarge { .. } => Self::new(6, "input_too_large", 413, GRPC_RESOURCE_EXHAUSTED, message),
+      BlockchainError::InputTooDeep { .. } => Self::new(7, "input_too_deep", 400, GRPC_INVALID_ARGUMENT, message),
+      BlockchainError::InvalidBlock(error) => Self { message, ..Self::from(error) },
+    }
+  }
+}
+
+impl From<&BlockValidationError> for ErrorStatus {
+  fn from(error: &BlockValidationError) -> Self {
+    let invalid = |code, kind| Self::new(code, kind, 422, GRPC_INVALID_ARGUMENT, error.to_string());
+    match error {
+      BlockValidationError::IdOutOfSequence => invalid(10, "id_out_of_sequence"),
+      BlockValidationError::PreviousHashMismatch => invalid(11, "previous_hash_mismatch"),
+      BlockValidationError::TooLarge => invalid(12, "too_large"),
+      BlockValidationError::InvalidTimestamp => invalid(13, "invalid_timestamp"),
+      BlockValidationError::DifficultyNotMet => invalid(14, "difficulty_not_met"),
+      BlockValidationError::HashMismatch => invalid(15, "hash_mismatch"),
+      BlockValidationError::InvalidSignature => invalid(17, "invalid_signature"),
+      BlockValidationError::Custom { rule, .. } => Self { rule: Some(rule.clone()), ..invalid(16, "custom_rule") },
+    }
+  }
+}
+
+#[test]
+fn codes_are_stable() {
+  let errors = [
+    BlockchainError::InvalidChainLength,
+    BlockchainError::MalformedJson("eof".to_string()),
+    BlockchainError::ChecksumMismatch,
+    BlockchainError::Io("denied".to_string()),
+    BlockchainError::UnknownChain("a".to_string()),
+    BlockchainError::InputTooLarge { size: 2, limit: 1 },
+    BlockchainError::InputTooDeep { limit: 1 },
+    BlockValidationError::IdOutOfSequence.into(),
+    BlockValidationError::PreviousHashMismatch.into(),
+    BlockValidationError::TooLarge.into(),
+    BlockValidationError::InvalidTimestamp.into(),
+    BlockValidationError::DifficultyNotMet.into(),
+    BlockValidationError::HashMismatch.into(),
+    BlockValidationError::Custom { rule: "r".to_string(), reason: "no".to_string() }.into(),
+    BlockValidationError::InvalidSignature.into(),
+  ];
+  let statuses = errors.iter()
+    .map(|error| { let status = ErrorStatus::from(error); (status.code, status.http_status, status.grpc_code) })
+    .collect::<Vec<_>>();
+  assert_eq!(statuses, [
+    (1, 409, 9), (2, 400, 3), (3, 422, 15), (4, 500, 13), (5, 404, 5), (6, 413, 8), (7, 400, 3),
+    (10, 422, 3), (11, 422, 3), (12, 422, 3), (13, 422, 3), (14, 422, 3), (15, 422, 3), (16, 422, 3), (17, 422, 3),
+  ]);
+}
+
+#[test]
+fn names_the_failing_custom_rule() {
+  let error = BlockchainError::InvalidBlock(BlockValidationError::Custom { rule: "no-shouting".to_string(), reason: "all caps".to_string() });
+  assert_eq!(
+    ErrorStatus::from(&error).to_json(),
+    r#"{"code":16,"kind":"custom_rule","message":"invalid block: no-shouting: all caps","rule":"no-shouting"}"#
+  );
+}
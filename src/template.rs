@@ -0,0 +1,142 @@
+use serde::{ Serialize, Deserialize };
+use crate::block::{ Block, BlockData };
+use crate::decode::{ self, DecodeLimits };
+use crate::difficulty::Difficulty;
+use crate::error::BlockchainError;
+use crate::miner::Miner;
+use crate::types::{ BlockHash, BlockId };
+
+/// Everything needed to mine a block, without the nonce and hash.
+///
+/// Templates serialize to JSON, so one can be written to a file, carried to
+/// another machine, mined there and the resulting block brought back.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BlockTemplate<T = String> {
+  pub id: BlockId,
+  pub previous_hash: BlockHash,
+  /// Seconds since the Unix epoch. Deserializes from an RFC 3339 string too.
+  #[serde(deserialize_with = "crate::timestamp::deserialize")]
+  pub timestamp: i64,
+  pub data: T,
+  /// The difficulty the block must meet, from the chain's params at its height. Not part of the block.
+  /// Templates written before it existed deserialize with the default difficulty.
+  #[serde(default)]
+  pub difficulty: Difficulty,
+}
+
+impl<T: BlockData> BlockTemplate<T> {
+  /// Serializes the template to JSON.
+  ///
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::template::BlockTemplate;
+  /// # use simple_blockchain::difficulty::Difficulty;
+  /// # use simple_blockchain::types::BlockId;
+  /// let template = BlockTemplate {
+  ///   id: BlockId(1),
+  ///   previous_hash: "genesis".into(),
+  ///   timestamp: 1643223669,
+  ///   data: "next".to_string(),
+  ///   difficulty: Difficulty::MEDIUM
+  /// };
+  /// assert_eq!(BlockTemplate::from_json(&template.to_json()), Ok(template));
+  /// ```
+  pub fn to_json(&self) -> String {
+    serde_json::to_string(self).unwrap()
+  }
+
+  /// Parses a template previously produced by [`BlockTemplate::to_json`].
+  ///
+  /// # Errors
+  /// Returns [`BlockchainError::InputTooLarge`] or [`BlockchainError::InputTooDeep`] if the input is over the default [`DecodeLimits`],
+  /// and [`BlockchainError::MalformedJson`] if it is not a template.
+  /// ```
+  /// # use simple_blockchain::template::BlockTemplate;
+  /// # use simple_blockchain::types::BlockId;
+  /// # use simple_blockchain::error::BlockchainError;
+  /// assert!(matches!(BlockTemplate::<String>::from_json("{}"), Err(BlockchainError::MalformedJson(_))));
+  /// ```
+  pub fn from_json(json: &str) -> Result<Self, BlockchainError> {
+    decode::from_json(json, DecodeLimits::default())
+  }
+
+  /// Mines the template into a block at its [`BlockTemplate::difficulty`]. Doesn't need access to the blockchain.
+  ///
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::template::BlockTemplate;
+  /// # use simple_blockchain::difficulty::Difficulty;
+  /// # use simple_blockchain::types::BlockId;
+  /// let template = BlockTemplate {
+  ///   id: BlockId(1),
+  ///   previous_hash: "genesis".into(),
+  ///   timestamp: 1643223669,
+  ///   data: "next".to_string(),
+  ///   difficulty: Difficulty::MEDIUM
+  /// };
+  /// let block = template.mine();
+  /// assert_eq!(block.id, 1);
+  /// assert_eq!(block.timestamp, 1643223669);
+  /// assert!(block.hash.starts_with("0000"));
+  /// ```
+  pub fn mine(self) -> Block<T> {
+    Miner.mine(self)
+  }
+
+  pub(crate) fn seal(self, nonce: u64, hash: String) -> Block<T> {
+    Block {
+      id: self.id,
+      hash: hash.into(),
+      previous_hash: self.previous_hash,
+      timestamp: self.timestamp,
+      data: self.data,
+      nonce
+    }
+  }
+}
+
+#[test]
+fn round_trips_through_json() {
+  let template = BlockTemplate {
+    id: BlockId(69),
+    previous_hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
+    timestamp: 1643220097,
+    data: "foo".to_string(),
+    difficulty: Difficulty::MEDIUM
+  };
+  assert_eq!(BlockTemplate::from_json(&template.to_json()), Ok(template));
+}
+
+#[test]
+fn mines_a_template() {
+  let template = BlockTemplate {
+    id: BlockId(69),
+    previous_hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
+    timestamp: 1643220097,
+    data: "foo".to_string(),
+    difficulty: Difficulty::MEDIUM
+  };
+  let block = template.mine();
+  assert_eq!(block.nonce, 9386);
+  assert_eq!(block.hash, "00007751f1b92a8ac1bdc88407e7a85b4c0dd59313d8fa78ae2208dbcaaad604".to_string());
+}
+
+#[test]
+fn mines_at_the_difficulty_of_the_chain_it_came_from() {
+  use crate::blockchain::Blockchain;
+  use crate::params::ChainParams;
+  let mut chain = Blockchain::with_params(ChainParams { difficulty: Difficulty(18), ..Default::default() });
+  assert!(chain.genesis().is_ok());
+  let exported = chain.block_template("next".to_string()).unwrap().to_json();
+
+  let template = BlockTemplate::<String>::from_json(&exported).unwrap();
+  assert_eq!(template.difficulty, Difficulty(18));
+  let block = template.mine();
+  assert_eq!(chain.submit_mined(&serde_json::to_string(&block).unwrap()), Ok(()));
+}
+
+#[test]
+fn reads_templates_written_without_a_difficulty() {
+  let json = r#"{"id":1,"previous_hash":"genesis","timestamp":1643223669,"data":"next"}"#;
+  assert_eq!(BlockTemplate::<String>::from_json(json).map(|template| template.difficulty), Ok(Difficulty::default()));
+}
@@ -0,0 +1,66 @@
+use chrono::{ DateTime, SecondsFormat, Utc };
+use serde::{ Deserialize, Deserializer, de::Error };
+
+/// How timestamps are written in JSON output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimestampFormat {
+  /// Seconds since the Unix epoch, e.g. `1643223669`. This is what the hash is computed over.
+  #[default]
+  Unix,
+  /// An RFC 3339 string in UTC, e.g. `"2022-01-26T19:01:09Z"`.
+  /// Timestamps outside years 0 to 9999, which RFC 3339 can't express, are still written as Unix seconds.
+  Rfc3339,
+}
+
+/// Converts seconds since the Unix epoch to a date and time.
+/// Timestamps outside the range chrono can represent saturate to its earliest or latest date.
+///
+/// # Examples
+/// ```
+/// # use simple_blockchain::timestamp::to_datetime;
+/// assert_eq!(to_datetime(1643223669).to_rfc3339(), "2022-01-26T19:01:09+00:00");
+/// ```
+pub fn to_datetime(timestamp: i64) -> DateTime<Utc> {
+  DateTime::from_timestamp(timestamp, 0)
+    .unwrap_or(if timestamp < 0 { DateTime::<Utc>::MIN_UTC } else { DateTime::<Utc>::MAX_UTC })
+}
+
+/// Formats seconds since the Unix epoch as an RFC 3339 string in UTC.
+///
+/// # Examples
+/// ```
+/// # use simple_blockchain::timestamp::to_rfc3339;
+/// assert_eq!(to_rfc3339(1643223669), "2022-01-26T19:01:09Z");
+/// ```
+pub fn to_rfc3339(timestamp: i64) -> String {
+  to_datetime(timestamp).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+// Formats the timestamp as RFC 3339 only if the string parses back to the same timestamp.
+pub(crate) fn to_exact_rfc3339(timestamp: i64) -> Option<String> {
+  let text = to_rfc3339(timestamp);
+  DateTime::parse_from_rfc3339(&text).ok().filter(|datetime| datetime.timestamp() == timestamp).map(|_| text)
+}
+
+// Accepts either seconds since the Unix epoch or an RFC 3339 string.
+pub(crate) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i64, D::Error> {
+  #[derive(Deserialize)]
+  #[serde(untagged)]
+  enum Timestamp {
+    Unix(i64),
+    Rfc3339(String),
+  }
+
+  match Timestamp::deserialize(deserializer)? {
+    Timestamp::Unix(timestamp) => Ok(timestamp),
+    Timestamp::Rfc3339(text) => DateTime::parse_from_rfc3339(&text)
+      .map(|datetime| datetime.timestamp())
+      .map_err(D::Error::custom),
+  }
+}
+
+#[test]
+fn saturates_out_of_range_timestamps() {
+  assert_eq!(to_datetime(i64::MAX), DateTime::<Utc>::MAX_UTC);
+  assert_eq!(to_datetime(i64::MIN), DateTime::<Utc>::MIN_UTC);
+}
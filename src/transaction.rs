@@ -0,0 +1,267 @@
+use std::collections::HashSet;
+use serde::{ Serialize, Deserialize };
+use crate::block::{ Block, BlockData };
+use crate::blockchain::Blockchain;
+use crate::crypto::{ self, Keypair };
+use crate::error::{ BlockchainError, BlockchainError::*, BlockValidationError, BlockValidationError::InvalidSignature };
+use crate::params::ChainParams;
+use crate::rules::Rule;
+use crate::types::{ Amount, BlockHeight };
+
+/// A transfer of `amount` from `sender` to `recipient`, signed by the sender.
+///
+/// `nonce` counts the sender's transactions, so a transfer can't be replayed through [`Blockchain::mine_pending`]:
+/// only one transaction per sender and nonce is ever pending, and one whose sender and nonce are already
+/// in the chain is dropped instead of mined. Blocks mined elsewhere aren't checked for replays.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Transaction {
+  /// The sender's public key, as hex.
+  pub sender: String,
+  pub recipient: String,
+  pub amount: Amount,
+  /// The sender's signature over [`Transaction::signing_message`], as hex.
+  pub signature: String,
+  pub nonce: u64,
+}
+
+impl Transaction {
+  /// Creates a transfer from `keypair`'s public key, signed by it.
+  ///
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::crypto::Keypair;
+  /// # use simple_blockchain::transaction::Transaction;
+  /// # use simple_blockchain::types::Amount;
+  /// let alice = Keypair::generate();
+  /// let transfer = Transaction::signed(&alice, "bob", Amount(5), 0);
+  /// assert_eq!(transfer.sender, alice.public_key());
+  /// assert!(transfer.has_valid_signature());
+  /// assert!(!Transaction { amount: Amount(500), ..transfer }.has_valid_signature());
+  /// ```
+  pub fn signed(keypair: &Keypair, recipient: impl Into<String>, amount: Amount, nonce: u64) -> Self {
+    let mut transaction = Self { sender: keypair.public_key(), recipient: recipient.into(), amount, signature: String::new(), nonce };
+    transaction.signature = keypair.sign(&transaction.signing_message());
+    transaction
+  }
+
+  /// Returns the bytes the sender signs: compact JSON of every field but the signature.
+  pub fn signing_message(&self) -> Vec<u8> {
+    let content = serde_json::json!({
+      "sender": self.sender,
+      "recipient": self.recipient,
+      "amount": self.amount,
+      "nonce": self.nonce
+    });
+    content.to_string().into_bytes()
+  }
+
+  /// Returns `true` if the signature is the sender's, over this transaction.
+  pub fn has_valid_signature(&self) -> bool {
+    crypto::verify(&self.sender, &self.signing_message(), &self.signature)
+  }
+}
+
+/// Blocks of transactions, mined by [`Blockchain::mine_pending`].
+impl BlockData for Vec<Transaction> {}
+
+/// Every transaction in a block must be signed by its sender.
+/// Not a default rule; add it to the chain's params with [`ChainParams::with_rule`].
+/// Params holding it can be serialized but not deserialized; see [`ChainParams`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TransactionSignatures;
+
+impl Rule<Vec<Transaction>> for TransactionSignatures {
+  fn name(&self) -> &str {
+    "transaction-signatures"
+  }
+
+  fn check(&self, block: &Block<Vec<Transaction>>, _: &Block<Vec<Transaction>>, _: &ChainParams<Vec<Transaction>>) -> Result<(), BlockValidationError> {
+    if !block.data.iter().all(Transaction::has_valid_signature) { return Err(InvalidSignature) };
+    Ok(())
+  }
+}
+
+/// Transactions waiting to be mined, in the order they arrived.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Mempool {
+  pending: Vec<Transaction>,
+}
+
+impl Mempool {
+  /// Creates an empty pool.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Adds a transaction. Returns `false`, leaving the pool as it was, if the signature isn't the sender's
+  /// or a transaction from the same sender with the same nonce is already pending.
+  ///
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::crypto::Keypair;
+  /// # use simple_blockchain::transaction::{ Mempool, Transaction };
+  /// # use simple_blockchain::types::Amount;
+  /// let alice = Keypair::generate();
+  /// let transfer = Transaction::signed(&alice, "bob", Amount(5), 0);
+  /// let mut mempool = Mempool::new();
+  /// assert!(mempool.add(transfer.clone()));
+  /// assert!(!mempool.add(Transaction::signed(&alice, "bob", Amount(500), 0)));
+  /// assert!(!mempool.add(Transaction { nonce: 1, ..transfer }));
+  /// assert_eq!(mempool.len(), 1);
+  /// ```
+  pub fn add(&mut self, transaction: Transaction) -> bool {
+    let duplicate = self.pending.iter()
+      .any(|pending| pending.sender == transaction.sender && pending.nonce == transaction.nonce);
+    let accepted = !duplicate && transaction.has_valid_signature();
+    if accepted { self.pending.push(transaction) };
+    accepted
+  }
+
+  /// Returns the pending transactions, oldest first.
+  pub fn pending(&self) -> impl Iterator<Item = &Transaction> {
+    self.pending.iter()
+  }
+
+  /// Returns the number of pending transactions.
+  pub fn len(&self) -> usize {
+    self.pending.len()
+  }
+
+  /// Returns `true` if no transactions are pending.
+  pub fn is_empty(&self) -> bool {
+    self.pending.is_empty()
+  }
+}
+
+impl Blockchain<Block<Vec<Transaction>>> {
+  /// Mines the oldest pending transactions in `mempool` into the next block and adds it.
+  /// Takes as many as fit in the next block's [`ChainParams::max_block_size`];
+  /// the rest stay pending. Returns how many were mined, and mines nothing if none are pending.
+  ///
+  /// Pending transactions that could never be mined are dropped first:
+  /// ones whose sender and nonce are already in the chain, and ones too big for a block on their own.
+  ///
+  /// # Examples
+  /// ```
+  /// # use simple_blockchain::block::Block;
+  /// # use simple_blockchain::blockchain::Blockchain;
+  /// # use simple_blockchain::crypto::Keypair;
+  /// # use simple_blockchain::difficulty::Difficulty;
+  /// # use simple_blockchain::params::ChainParams;
+  /// # use simple_blockchain::transaction::{ Mempool, Transaction };
+  /// # use simple_blockchain::types::Amount;
+  /// let mut ledger = Blockchain::with_params(ChainParams { difficulty: Difficulty::DEMO, ..Default::default() });
+  /// ledger.genesis_with(vec![]).unwrap();
+  /// let alice = Keypair::generate();
+  /// let mut mempool = Mempool::new();
+  /// for nonce in 0..3 {
+  ///   mempool.add(Transaction::signed(&alice, "bob", Amount(5), nonce));
+  /// }
+  ///
+  /// assert_eq!(ledger.mine_pending(&mut mempool), Ok(3));
+  /// assert_eq!(ledger.blocks[1].data.len(), 3);
+  /// assert!(mempool.is_empty());
+  /// assert!(ledger.is_chain_valid());
+  ///
+  /// mempool.add(Transaction::signed(&alice, "bob", Amount(5), 0));
+  /// assert_eq!(ledger.mine_pending(&mut mempool), Ok(0));
+  /// assert!(mempool.is_empty());
+  /// ```
+  ///
+  /// # Errors
+  /// Returns [`BlockchainError::InvalidChainLength`] if the blockchain is empty,
+  /// and the same errors as [`Blockchain::add_block`] if the mined block is rejected,
+  /// in which case the transactions stay pending.
+  pub fn mine_pending(&mut self, mempool: &mut Mempool) -> Result<usize, BlockchainError> {
+    if self.blocks.is_empty() { return Err(InvalidChainLength) };
+
+    let params = self.params.at_height(BlockHeight(self.blocks.len() as u64));
+    let mut template = self.block_template(vec![])?;
+    // The size of the block with no transactions and the longest possible nonce, then one transaction at a time.
+    let mut size = template.clone().seal(u64::MAX, String::new()).size();
+    let mined = self.blocks.iter()
+      .flat_map(|block| &block.data)
+      .map(|transaction| (transaction.sender.as_str(), transaction.nonce))
+      .collect::<HashSet<_>>();
+    mempool.pending.retain(|transaction| !mined.contains(&(transaction.sender.as_str(), transaction.nonce))
+      && size + encoded_size(transaction) <= params.max_block_size);
+
+    let mut count = 0;
+    for transaction in mempool.pending() {
+      let added = encoded_size(transaction) + usize::from(count > 0);
+      if size + added > params.max_block_size { break };
+      size += added;
+      count += 1;
+    }
+    if count == 0 { return Ok(0) };
+
+    template.data = mempool.pending[..count].to_vec();
+    self.add_block(template.mine())?;
+    mempool.pending.drain(..count);
+    Ok(count)
+  }
+}
+
+fn encoded_size(transaction: &Transaction) -> usize {
+  serde_json::to_vec(transaction).unwrap().len()
+}
+
+#[test]
+fn leaves_what_does_not_fit_pending() {
+  use crate::difficulty::Difficulty;
+  let alice = Keypair::from_seed([1; 32]);
+  let transfer = |nonce| Transaction::signed(&alice, "bob", Amount(5), nonce);
+  let one = serde_json::to_vec(&transfer(0)).unwrap().len();
+  let mut ledger = Blockchain::with_params(ChainParams { difficulty: Difficulty::DEMO, max_block_size: 250 + 2 * one, ..Default::default() });
+  ledger.genesis_with(vec![]).unwrap();
+  let mut mempool = Mempool::new();
+  for nonce in 0..5 {
+    assert!(mempool.add(transfer(nonce)));
+  }
+
+  assert_eq!(ledger.mine_pending(&mut mempool), Ok(2));
+  assert!(ledger.blocks[1].size() <= ledger.params.max_block_size);
+  assert_eq!(ledger.blocks[1].data, [transfer(0), transfer(1)]);
+  assert_eq!(mempool.pending().map(|transaction| transaction.nonce).collect::<Vec<_>>(), [2, 3, 4]);
+  assert_eq!(ledger.mine_pending(&mut mempool), Ok(2));
+  assert_eq!(ledger.mine_pending(&mut mempool), Ok(1));
+  assert_eq!(ledger.mine_pending(&mut mempool), Ok(0));
+  assert_eq!(ledger.blocks.len(), 4);
+  assert!(ledger.is_chain_valid());
+}
+
+#[test]
+fn mines_nothing_without_a_chain() {
+  let mut mempool = Mempool::new();
+  assert!(mempool.add(Transaction::signed(&Keypair::from_seed([1; 32]), "bob", Amount(1), 0)));
+  let mut ledger = Blockchain::<Block<Vec<Transaction>>>::with_params(ChainParams::default());
+  assert_eq!(ledger.mine_pending(&mut mempool), Err(InvalidChainLength));
+  assert_eq!(mempool.len(), 1);
+}
+
+#[test]
+fn drops_transactions_too_big_for_any_block() {
+  use crate::difficulty::Difficulty;
+  let alice = Keypair::from_seed([1; 32]);
+  let mut ledger = Blockchain::with_params(ChainParams { difficulty: Difficulty::DEMO, max_block_size: 600, ..Default::default() });
+  ledger.genesis_with(vec![]).unwrap();
+  let mut mempool = Mempool::new();
+  assert!(mempool.add(Transaction::signed(&alice, "b".repeat(600), Amount(5), 0)));
+  assert!(mempool.add(Transaction::signed(&alice, "bob", Amount(5), 1)));
+
+  assert_eq!(ledger.mine_pending(&mut mempool), Ok(1));
+  assert_eq!(ledger.blocks[1].data[0].nonce, 1);
+  assert!(mempool.is_empty());
+}
+
+#[test]
+fn checks_transaction_signatures_when_the_rule_is_installed() {
+  use crate::difficulty::Difficulty;
+  let params = ChainParams { difficulty: Difficulty::DEMO, ..Default::default() }.with_rule(TransactionSignatures);
+  let mut ledger = Blockchain::with_params(params);
+  ledger.genesis_with(vec![]).unwrap();
+  let forged = Transaction { amount: Amount(500), ..Transaction::signed(&Keypair::from_seed([1; 32]), "bob", Amount(5), 0) };
+  let template = ledger.block_template(vec![forged]).unwrap();
+  let block = template.mine();
+  assert_eq!(ledger.add_block(block), Err(InvalidBlock(InvalidSignature)));
+}
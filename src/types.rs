@@ -0,0 +1,299 @@
+use std::fmt;
+use std::ops::{ Add, Deref, Sub };
+use serde::{ Serialize, Deserialize };
+use crate::error::AmountError;
+
+/// A block's id, which counts up by one from the genesis block.
+///
+/// Converts from and compares with `u64`, so plain numbers still work where ids are expected.
+/// Adding to an id panics on overflow; use [`BlockId::checked_add`] on ids from untrusted input.
+///
+/// # Examples
+/// ```
+/// # use simple_blockchain::types::BlockId;
+/// let id = BlockId(1);
+/// assert_eq!(id + 1, BlockId(2));
+/// assert_eq!(id, 1);
+/// assert_eq!(u64::from(id), 1);
+/// assert_eq!(BlockId(u64::MAX).checked_add(1), None);
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[serde(transparent)]
+pub struct BlockId(pub u64);
+
+/// A position in a blockchain, counted from the genesis block at height 0.
+/// Adding and subtracting panic on overflow; the `checked_` methods return `None` instead.
+///
+/// # Examples
+/// ```
+/// # use simple_blockchain::types::BlockHeight;
+/// assert_eq!(BlockHeight(5) - BlockHeight(2), 3);
+/// assert_eq!(BlockHeight(2) + 3, BlockHeight(5));
+/// assert_eq!(BlockHeight(2).checked_sub(BlockHeight(5)), None);
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[serde(transparent)]
+pub struct BlockHeight(pub u64);
+
+/// A block's hash, normally 64 lowercase hex digits. The genesis block's previous hash is `"genesis"`.
+///
+/// Dereferences to `str` and compares with string types, so it can be used wherever a hash string was.
+///
+/// # Examples
+/// ```
+/// # use simple_blockchain::types::BlockHash;
+/// let hash = BlockHash::from("0000ff");
+/// assert_eq!(hash, "0000ff");
+/// assert!(hash.starts_with("0000"));
+/// assert_eq!(String::from(hash), "0000ff".to_string());
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[serde(transparent)]
+pub struct BlockHash(pub String);
+
+/// An amount of value, counted in the smallest indivisible unit.
+///
+/// There is deliberately no `+` or `-`: arithmetic is checked and fails with an [`AmountError`]
+/// instead of wrapping or panicking.
+///
+/// # Examples
+/// ```
+/// # use simple_blockchain::error::AmountError;
+/// # use simple_blockchain::types::Amount;
+/// let balance = Amount(10);
+/// assert_eq!(balance.checked_sub(Amount(3)), Ok(Amount(7)));
+/// assert_eq!(balance.checked_sub(Amount(11)), Err(AmountError::Underflow));
+/// assert_eq!(Amount::MAX.checked_add(Amount(1)), Err(AmountError::Overflow));
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[serde(transparent)]
+pub struct Amount(pub u64);
+
+impl From<u64> for BlockId {
+  fn from(id: u64) -> Self {
+    Self(id)
+  }
+}
+
+impl From<BlockId> for u64 {
+  fn from(id: BlockId) -> Self {
+    id.0
+  }
+}
+
+impl PartialEq<u64> for BlockId {
+  fn eq(&self, other: &u64) -> bool {
+    self.0 == *other
+  }
+}
+
+impl PartialEq<BlockId> for u64 {
+  fn eq(&self, other: &BlockId) -> bool {
+    *self == other.0
+  }
+}
+
+impl BlockId {
+  /// Returns the id `rhs` after this one, or `None` if that's past `u64::MAX`.
+  pub fn checked_add(self, rhs: u64) -> Option<BlockId> {
+    self.0.checked_add(rhs).map(BlockId)
+  }
+}
+
+impl Add<u64> for BlockId {
+  type Output = BlockId;
+
+  fn add(self, rhs: u64) -> BlockId {
+    self.checked_add(rhs).expect("block id overflow")
+  }
+}
+
+impl fmt::Display for BlockId {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.0)
+  }
+}
+
+impl From<u64> for BlockHeight {
+  fn from(height: u64) -> Self {
+    Self(height)
+  }
+}
+
+impl From<BlockHeight> for u64 {
+  fn from(height: BlockHeight) -> Self {
+    height.0
+  }
+}
+
+impl PartialEq<u64> for BlockHeight {
+  fn eq(&self, other: &u64) -> bool {
+    self.0 == *other
+  }
+}
+
+impl PartialEq<BlockHeight> for u64 {
+  fn eq(&self, other: &BlockHeight) -> bool {
+    *self == other.0
+  }
+}
+
+impl BlockHeight {
+  /// Returns the height `rhs` above this one, or `None` if that's past `u64::MAX`.
+  pub fn checked_add(self, rhs: u64) -> Option<BlockHeight> {
+    self.0.checked_add(rhs).map(BlockHeight)
+  }
+
+  /// Returns how many blocks this height is above `rhs`, or `None` if it's below it.
+  pub fn checked_sub(self, rhs: BlockHeight) -> Option<u64> {
+    self.0.checked_sub(rhs.0)
+  }
+}
+
+impl Add<u64> for BlockHeight {
+  type Output = BlockHeight;
+
+  fn add(self, rhs: u64) -> BlockHeight {
+    self.checked_add(rhs).expect("block height overflow")
+  }
+}
+
+impl Sub for BlockHeight {
+  type Output = u64;
+
+  fn sub(self, rhs: BlockHeight) -> u64 {
+    self.checked_sub(rhs).expect("block height underflow")
+  }
+}
+
+impl fmt::Display for BlockHeight {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.0)
+  }
+}
+
+impl From<String> for BlockHash {
+  fn from(hash: String) -> Self {
+    Self(hash)
+  }
+}
+
+impl From<&str> for BlockHash {
+  fn from(hash: &str) -> Self {
+    Self(hash.to_string())
+  }
+}
+
+impl From<BlockHash> for String {
+  fn from(hash: BlockHash) -> Self {
+    hash.0
+  }
+}
+
+impl Deref for BlockHash {
+  type Target = str;
+
+  fn deref(&self) -> &str {
+    &self.0
+  }
+}
+
+impl AsRef<str> for BlockHash {
+  fn as_ref(&self) -> &str {
+    &self.0
+  }
+}
+
+impl PartialEq<str> for BlockHash {
+  fn eq(&self, other: &str) -> bool {
+    self.0 == other
+  }
+}
+
+impl PartialEq<&str> for BlockHash {
+  fn eq(&self, other: &&str) -> bool {
+    self.0 == *other
+  }
+}
+
+impl PartialEq<String> for BlockHash {
+  fn eq(&self, other: &String) -> bool {
+    &self.0 == other
+  }
+}
+
+impl PartialEq<BlockHash> for str {
+  fn eq(&self, other: &BlockHash) -> bool {
+    self == other.0
+  }
+}
+
+impl PartialEq<BlockHash> for &str {
+  fn eq(&self, other: &BlockHash) -> bool {
+    *self == other.0
+  }
+}
+
+impl PartialEq<BlockHash> for String {
+  fn eq(&self, other: &BlockHash) -> bool {
+    *self == other.0
+  }
+}
+
+impl fmt::Display for BlockHash {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.0)
+  }
+}
+
+impl Amount {
+  pub const ZERO: Amount = Amount(0);
+  pub const MAX: Amount = Amount(u64::MAX);
+
+  /// Adds two amounts.
+  ///
+  /// # Errors
+  /// Returns [`AmountError::Overflow`] if the sum doesn't fit.
+  pub fn checked_add(self, rhs: Amount) -> Result<Amount, AmountError> {
+    self.0.checked_add(rhs.0).map(Amount).ok_or(AmountError::Overflow)
+  }
+
+  /// Subtracts `rhs` from this amount.
+  ///
+  /// # Errors
+  /// Returns [`AmountError::Underflow`] if `rhs` is larger.
+  pub fn checked_sub(self, rhs: Amount) -> Result<Amount, AmountError> {
+    self.0.checked_sub(rhs.0).map(Amount).ok_or(AmountError::Underflow)
+  }
+
+  /// Adds up amounts, e.g. the outputs of a transfer.
+  ///
+  /// # Errors
+  /// Returns [`AmountError::Overflow`] if the total doesn't fit.
+  pub fn checked_sum(amounts: impl IntoIterator<Item = Amount>) -> Result<Amount, AmountError> {
+    amounts.into_iter().try_fold(Amount::ZERO, Amount::checked_add)
+  }
+}
+
+impl fmt::Display for Amount {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.0)
+  }
+}
+
+#[test]
+fn serializes_transparently() {
+  assert_eq!(serde_json::to_string(&BlockId(3)).unwrap(), "3");
+  assert_eq!(serde_json::to_string(&BlockHeight(3)).unwrap(), "3");
+  assert_eq!(serde_json::to_string(&BlockHash::from("00ff")).unwrap(), r#""00ff""#);
+  assert_eq!(serde_json::from_str::<BlockHash>(r#""00ff""#).unwrap(), "00ff");
+}
+
+#[test]
+fn sums_amounts_without_overflowing() {
+  assert_eq!(Amount::checked_sum([Amount(1), Amount(2), Amount(3)]), Ok(Amount(6)));
+  assert_eq!(Amount::checked_sum([Amount::MAX, Amount(1)]), Err(AmountError::Overflow));
+  assert_eq!(Amount::checked_sum([]), Ok(Amount::ZERO));
+  assert_eq!(serde_json::to_string(&Amount(5)).unwrap(), "5");
+  assert_eq!(Amount(5).to_string(), "5");
+}
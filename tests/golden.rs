@@ -0,0 +1,101 @@
+//! Hashes and serializations of a fixed corpus, compared against the files in `tests/golden`.
+//!
+//! A failure means a consensus-relevant encoding changed. If the change is intended,
+//! regenerate the files with `UPDATE_GOLDEN=1 cargo test --test golden` and commit them.
+use std::fs;
+use std::path::PathBuf;
+use sha2::{ Sha256, Digest };
+use simple_blockchain::block::Block;
+use simple_blockchain::blockchain::Blockchain;
+use simple_blockchain::difficulty::Difficulty;
+use simple_blockchain::error::{ BlockchainError, BlockValidationError };
+use simple_blockchain::miner::Miner;
+use simple_blockchain::params::{ ChainParams, Upgrade };
+use simple_blockchain::status::ErrorStatus;
+use simple_blockchain::template::BlockTemplate;
+use simple_blockchain::timestamp::TimestampFormat;
+use simple_blockchain::types::BlockId;
+
+fn check(name: &str, actual: &str) {
+  let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(name);
+  if std::env::var_os("UPDATE_GOLDEN").is_some() {
+    fs::write(&path, actual).unwrap();
+    return;
+  }
+  let expected = fs::read_to_string(&path).unwrap_or_else(|_| panic!("missing golden file {}", path.display()));
+  assert_eq!(actual, expected, "{} no longer matches its golden file", name);
+}
+
+fn corpus() -> Vec<(&'static str, Block)> {
+  vec![
+    ("genesis", Block {
+      id: BlockId(0),
+      hash: "0000dbeb9e573d5382c63fd9a222c3720a4341b06416348fc5bbc0d19380a248".into(),
+      previous_hash: "genesis".into(),
+      timestamp: 1643223000,
+      data: "genesis!".to_string(),
+      nonce: 44475
+    }),
+    ("next", Block {
+      id: BlockId(1),
+      hash: "0000cc07887fb749c99974e8e93debb64e205086f6d0962ef17bf6f0bb295f3e".into(),
+      previous_hash: "0000dbeb9e573d5382c63fd9a222c3720a4341b06416348fc5bbc0d19380a248".into(),
+      timestamp: 1643223669,
+      data: "next".to_string(),
+      nonce: 236492
+    }),
+    ("unicode", Block {
+      id: BlockId(2),
+      hash: "not mined".into(),
+      previous_hash: "0000cc07887fb749c99974e8e93debb64e205086f6d0962ef17bf6f0bb295f3e".into(),
+      timestamp: 1643224000,
+      data: "\"quoted\" \\ tab\t ünïcödé ✓".to_string(),
+      nonce: 0
+    }),
+  ]
+}
+
+#[test]
+fn block_encodings_and_hashes() {
+  for (name, block) in corpus() {
+    check(&format!("{}.json", name), &block.to_json(TimestampFormat::Unix));
+    check(&format!("{}.rfc3339.json", name), &block.to_json(TimestampFormat::Rfc3339));
+    check(&format!("{}.preimage", name), &String::from_utf8(block.hash_preimage()).unwrap());
+    check(&format!("{}.hash", name), &hex::encode(Sha256::digest(&block.hash_preimage())));
+  }
+}
+
+#[test]
+fn mined_block() {
+  let template = BlockTemplate {
+    id: 69.into(),
+    previous_hash: "0000f816a87f806bb0073dcf026a64fb40c946b5abee2573702828694d5b4c43".into(),
+    timestamp: 1643220097,
+    data: "foo".to_string(),
+    difficulty: Difficulty::MEDIUM
+  };
+  check("template.json", &template.to_json());
+  check("mined.json", &Miner.mine(template).to_json(TimestampFormat::Unix));
+}
+
+#[test]
+fn chain_summary_and_params() {
+  let blocks = corpus().into_iter().take(2).map(|(_, block)| block).collect();
+  let chain = Blockchain::<Block> { blocks, params: ChainParams::default() };
+  check("summary.json", &chain.summary_json());
+
+  let params: ChainParams = ChainParams { difficulty: Difficulty::DEMO, ..Default::default() }
+    .with_upgrade(Upgrade { difficulty: Some(Difficulty::EASY), ..Upgrade::at(100) });
+  check("params.json", &serde_json::to_string(&params).unwrap());
+}
+
+#[test]
+fn error_statuses() {
+  let errors = [
+    BlockchainError::InvalidChainLength,
+    BlockchainError::InvalidBlock(BlockValidationError::HashMismatch),
+    BlockchainError::InvalidBlock(BlockValidationError::Custom { rule: "no-shouting".to_string(), reason: "all caps".to_string() }),
+  ];
+  let bodies = errors.iter().map(|error| ErrorStatus::from(error).to_json()).collect::<Vec<_>>();
+  check("errors.jsonl", &(bodies.join("\n") + "\n"));
+}
@@ -0,0 +1,84 @@
+//! A long-chain stress test, ignored by default. Run it in release mode:
+//!
+//! `cargo test --release --test stress -- --ignored --nocapture`
+//!
+//! `STRESS_BLOCKS` sets the chain length, one million by default.
+use std::time::{ Duration, Instant };
+use simple_blockchain::block::Block;
+use simple_blockchain::blockchain::Blockchain;
+use simple_blockchain::difficulty::Difficulty;
+use simple_blockchain::params::ChainParams;
+
+// Anything faster than this is too short to time reliably, so ratios of such times aren't checked.
+const NOISE: Duration = Duration::from_millis(50);
+
+struct Timings {
+  validation: Duration,
+  save: Duration,
+  load: Duration,
+  backup_size: u64,
+}
+
+fn validate_and_round_trip(chain: &Blockchain<Block>) -> Timings {
+  let start = Instant::now();
+  assert!(chain.is_chain_valid());
+  let validation = start.elapsed();
+
+  let dir = std::env::temp_dir().join(format!("simple_blockchain_stress_{}", std::process::id()));
+  let start = Instant::now();
+  chain.backup(&dir).unwrap();
+  let save = start.elapsed();
+  let start = Instant::now();
+  let mut restored = Blockchain::with_params(chain.params.clone());
+  let result = restored.restore(&dir);
+  let load = start.elapsed();
+  let backup_size = std::fs::metadata(dir.join("chain.json")).map(|metadata| metadata.len()).unwrap_or(0);
+  std::fs::remove_dir_all(&dir).unwrap();
+  assert_eq!(result, Ok(()));
+  assert_eq!(restored.blocks, chain.blocks);
+  Timings { validation, save, load, backup_size }
+}
+
+// Doubling the chain should about double a linear-time operation, and quadruple a quadratic one.
+fn assert_linear(name: &str, half: Duration, full: Duration) {
+  assert!(full < (half * 3).max(NOISE), "{} took {:?} at half length but {:?} at full length", name, half, full);
+}
+
+#[test]
+#[ignore]
+fn builds_validates_and_restores_a_long_chain() {
+  let length = std::env::var("STRESS_BLOCKS").ok().and_then(|blocks| blocks.parse().ok()).unwrap_or(1_000_000);
+  let mut chain = Blockchain::with_params(ChainParams { difficulty: Difficulty(0), ..Default::default() });
+  chain.genesis().unwrap();
+
+  let mut halves = [Duration::ZERO; 2];
+  let mut at_half = None;
+  for index in 1..length {
+    if index == length / 2 { at_half = Some(validate_and_round_trip(&chain)) };
+    let template = chain.block_template(format!("block {}", index)).unwrap();
+    let block = template.mine();
+    let start = Instant::now();
+    chain.add_block(block).unwrap();
+    halves[(2 * index / length).min(1)] += start.elapsed();
+  }
+  let add_time = halves[0] + halves[1];
+  let half = at_half.expect("STRESS_BLOCKS must be at least 4");
+  let full = validate_and_round_trip(&chain);
+
+  // What the blocks take up in memory: the vector's buffer plus each block's strings.
+  let block_memory = chain.blocks.capacity() * std::mem::size_of::<Block>()
+    + chain.blocks.iter().map(|block| block.hash.len() + block.previous_hash.len() + block.data.capacity()).sum::<usize>();
+  println!("blocks:          {}", length);
+  println!("add_block:       {:?} total, {:.0} blocks/s", add_time, length as f64 / add_time.as_secs_f64());
+  println!("is_chain_valid:  {:?} (half length {:?})", full.validation, half.validation);
+  println!("backup:          {:?}, {} bytes (half length {:?})", full.save, full.backup_size, half.save);
+  println!("restore:         {:?} (half length {:?})", full.load, half.load);
+  println!("block memory:    ~{} bytes, {} per block", block_memory, block_memory / length);
+
+  // Adding a block shouldn't get slower as the chain grows. A per-block cost proportional to the chain's
+  // length would make the second half of the adds about three times slower than the first.
+  assert!(halves[1] < (halves[0] * 2).max(NOISE), "add_block slowed down as the chain grew: {:?} then {:?}", halves[0], halves[1]);
+  assert_linear("is_chain_valid", half.validation, full.validation);
+  assert_linear("backup", half.save, full.save);
+  assert_linear("restore", half.load, full.load);
+}